@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueStatus {
+    Pending,
+    Processing,
+    Done,
+    Failed,
+}
+
+impl QueueStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueueStatus::Pending => "Pending",
+            QueueStatus::Processing => "Processing",
+            QueueStatus::Done => "Done",
+            QueueStatus::Failed => "Failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QueueItem {
+    pub path: PathBuf,
+    pub status: QueueStatus,
+    pub message: String,
+}
+
+// Races the watcher picked up for automatic processing, newest last. Only one worker drains the
+// queue at a time.
+#[derive(Default)]
+pub struct ProcessingQueue {
+    items: Vec<QueueItem>,
+    running: bool,
+}
+
+pub static PROCESSING_QUEUE: LazyLock<Mutex<ProcessingQueue>> =
+    LazyLock::new(|| Mutex::new(ProcessingQueue::default()));
+
+impl ProcessingQueue {
+    // A race already waiting isn't queued twice, and finished entries for it make way for the
+    // new one
+    pub fn push(&mut self, path: PathBuf) {
+        if self
+            .items
+            .iter()
+            .any(|x| x.path == path && x.status == QueueStatus::Pending)
+        {
+            return;
+        }
+
+        self.items.retain(|x| {
+            x.path != path || !matches!(x.status, QueueStatus::Done | QueueStatus::Failed)
+        });
+        self.items.push(QueueItem {
+            path,
+            status: QueueStatus::Pending,
+            message: String::new(),
+        });
+    }
+
+    // Whether the caller should start a worker
+    pub fn start(&mut self) -> bool {
+        if self.running {
+            return false;
+        }
+        self.running = true;
+
+        true
+    }
+
+    // Takes the oldest pending race, or stops the worker once there are none left
+    pub fn next(&mut self) -> Option<PathBuf> {
+        match self
+            .items
+            .iter_mut()
+            .find(|x| x.status == QueueStatus::Pending)
+        {
+            Some(item) => {
+                item.status = QueueStatus::Processing;
+                Some(item.path.clone())
+            }
+            None => {
+                self.running = false;
+                None
+            }
+        }
+    }
+
+    pub fn finish(&mut self, path: &Path, result: Result<String, String>) {
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .find(|x| x.path == path && x.status == QueueStatus::Processing)
+        {
+            match result {
+                Ok(message) => {
+                    item.status = QueueStatus::Done;
+                    item.message = message;
+                }
+                Err(e) => {
+                    item.status = QueueStatus::Failed;
+                    item.message = e;
+                }
+            }
+        }
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.items
+            .retain(|x| !matches!(x.status, QueueStatus::Done | QueueStatus::Failed));
+    }
+
+    pub fn items(&self) -> &Vec<QueueItem> {
+        &self.items
+    }
+}
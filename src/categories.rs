@@ -0,0 +1,203 @@
+use crate::history::{now_secs, season_of};
+use crate::parse::{RaceEvent, split_csv_line};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default)]
+pub struct Registration {
+    pub birth_year: Option<i64>,
+    pub gender: String,
+    // Given outright by the registration file, overrides the rules
+    pub category: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CategoryRule {
+    pub category: String,
+    // Blank matches any gender
+    pub gender: String,
+    pub min_age: i64,
+    pub max_age: i64,
+}
+
+// One rule per line as "category,gender,min age,max age", e.g. "Junior B Ladies,F,15,16". Ages are
+// taken at the start of the season, and the first matching rule wins.
+pub fn parse_category_rules(raw: &str) -> Result<Vec<CategoryRule>, String> {
+    let mut out = vec![];
+    for line in raw.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let split = line.split(",").map(|x| x.trim()).collect::<Vec<_>>();
+        if split.len() != 4 {
+            return Err(format!("Category rule \"{}\" needs 4 fields", line));
+        }
+
+        let age = |x: &str| {
+            x.parse::<i64>()
+                .map_err(|_| format!("Invalid age \"{}\" in category rule \"{}\"", x, line))
+        };
+        out.push(CategoryRule {
+            category: split[0].to_string(),
+            gender: split[1].to_string(),
+            min_age: age(split[2])?,
+            max_age: age(split[3])?,
+        });
+    }
+
+    Ok(out)
+}
+
+// Reads either a registration CSV with a header row naming "id" and any of "birth_year",
+// "gender" and "category" columns, or a FinishLynx people file laid out as
+// "id,last name,first name,affiliation,birth year,gender,category"
+pub fn parse_registrations(raw: &str) -> HashMap<u32, Registration> {
+    let mut lines = raw.lines().filter(|x| !x.trim().is_empty()).peekable();
+
+    let header = match lines.peek() {
+        Some(first) => split_csv_line(first)
+            .iter()
+            .map(|x| x.to_lowercase().replace(" ", "_"))
+            .collect::<Vec<_>>(),
+        None => return HashMap::new(),
+    };
+    let has_header = header.iter().any(|x| x == "id");
+    let column = |names: &[&str], fallback: usize| {
+        if has_header {
+            header.iter().position(|x| names.contains(&x.as_str()))
+        } else {
+            Some(fallback)
+        }
+    };
+
+    let id_idx = column(&["id"], 0);
+    let birth_idx = column(&["birth_year", "birthyear", "yob"], 4);
+    let gender_idx = column(&["gender", "sex"], 5);
+    let category_idx = column(&["category"], 6);
+
+    if has_header {
+        lines.next();
+    }
+
+    let mut out = HashMap::new();
+    for line in lines {
+        let fields = split_csv_line(line);
+        let field = |idx: Option<usize>| {
+            idx.and_then(|i| fields.get(i))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        if let Ok(id) = field(id_idx).parse::<u32>() {
+            out.insert(
+                id,
+                Registration {
+                    // Full birth dates are fine too, only the year is used
+                    birth_year: field(birth_idx)
+                        .split(|x: char| !x.is_ascii_digit())
+                        .find(|x| x.len() == 4)
+                        .and_then(|x| x.parse::<i64>().ok()),
+                    gender: field(gender_idx),
+                    category: field(category_idx),
+                },
+            );
+        }
+    }
+
+    out
+}
+
+pub fn load_registrations(path: &str) -> Result<HashMap<u32, Registration>, String> {
+    if path.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let raw =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}, {}", path, e))?;
+    Ok(parse_registrations(&raw))
+}
+
+fn categorize(registration: &Registration, rules: &Vec<CategoryRule>, season: i64) -> String {
+    if !registration.category.is_empty() {
+        return registration.category.clone();
+    }
+
+    let age = match registration.birth_year {
+        Some(x) => season - x,
+        None => return String::new(),
+    };
+
+    rules
+        .iter()
+        .find(|x| {
+            (x.gender.is_empty() || x.gender.eq_ignore_ascii_case(&registration.gender))
+                && age >= x.min_age
+                && age <= x.max_age
+        })
+        .map(|x| x.category.clone())
+        .unwrap_or_default()
+}
+
+// Assigns each competitor a gender and category, then ranks them by time within their category.
+// Ages are taken in the season of `race_date`, or the current season if the date isn't known.
+pub fn apply_categories(
+    event: &mut RaceEvent,
+    registrations: &HashMap<u32, Registration>,
+    rules: &Vec<CategoryRule>,
+    race_date: Option<u64>,
+) {
+    let season = season_of(race_date.unwrap_or_else(now_secs));
+
+    for competitor in event.competitors.iter_mut() {
+        competitor.category_place = None;
+        match competitor.skater_id.and_then(|x| registrations.get(&x)) {
+            Some(registration) => {
+                competitor.gender = registration.gender.clone();
+                competitor.category = categorize(registration, rules, season);
+            }
+            None => {
+                competitor.gender = String::new();
+                competitor.category = String::new();
+            }
+        }
+    }
+
+    let mut timed = event
+        .competitors
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| !x.category.is_empty())
+        .filter_map(|(i, x)| match x.time {
+            Some(t) if !t.is_no_time() && x.place.unwrap_or(255) != 255 => {
+                Some((i, x.category.clone(), t.total_secs()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    timed.sort_by(|x, y| x.1.cmp(&y.1).then(x.2.total_cmp(&y.2)));
+
+    let mut cur_category = String::new();
+    let mut place: u8 = 0;
+    for (i, category, _) in timed {
+        if category != cur_category {
+            cur_category = category;
+            place = 0;
+        }
+        place = place.saturating_add(1);
+        event.competitors[i].category_place = Some(place);
+    }
+}
+
+// Categories present in the race, in the order their fastest skater finished
+pub fn categories_in(event: &RaceEvent) -> Vec<String> {
+    let mut out: Vec<String> = vec![];
+    let mut ordered = event.competitors.iter().collect::<Vec<_>>();
+    ordered.sort_by_key(|x| x.place.unwrap_or(255));
+    for competitor in ordered {
+        if !competitor.category.is_empty() && !out.contains(&competitor.category) {
+            out.push(competitor.category.clone());
+        }
+    }
+
+    out
+}
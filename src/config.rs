@@ -1,11 +1,100 @@
 use crate::SettingsData;
+use crate::status::report_error;
 use slint::{Model, ModelRc, SharedString, VecModel};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
 
+// What a new search path lists
+pub const DEFAULT_INCLUDE_GLOBS: &str = "*.lif";
+
+// The last settings read or written, used when the file can't be read
+static LAST_GOOD: Mutex<Option<String>> = Mutex::new(None);
+
+// Fields missing from older config files fall back to the defaults below
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 struct SettingsDataAnalog {
     paths: Vec<String>,
+    // Parallel to paths, whether to look in subfolders too and which files to list
+    recursive_paths: Vec<bool>,
+    include_globs: Vec<String>,
+    exclude_globs: Vec<String>,
+    stability_interval_ms: i32,
     pdf_output_enabled: bool,
     pdf_output_path: String,
+    output_name_template: String,
+    output_policy: i32,
+    auto_process_enabled: bool,
+    auto_print_enabled: bool,
+    auto_print_printer: String,
+    auto_print_copies: i32,
+    progression_enabled: bool,
+    advance_top_per_heat: i32,
+    advance_best_times: i32,
+    next_round_heat_size: i32,
+    seeding_method: i32,
+    draw_table: String,
+    evt_output_enabled: bool,
+    evt_output_path: String,
+    mass_start_enabled: bool,
+    sprint_points: String,
+    finish_points: String,
+    team_counting_skater: i32,
+    pdf_gap_to_leader: bool,
+    pdf_gap_to_previous: bool,
+    pdf_speed: bool,
+    pdf_lap_average: bool,
+    // Percent of the screen's pixels the preview is rendered at
+    preview_resolution: i32,
+    track_type: i32,
+    records_path: String,
+    registration_path: String,
+    category_rules: String,
+    club_points: String,
+}
+
+impl Default for SettingsDataAnalog {
+    fn default() -> Self {
+        Self {
+            paths: vec![],
+            recursive_paths: vec![],
+            include_globs: vec![],
+            exclude_globs: vec![],
+            stability_interval_ms: 500,
+            pdf_output_enabled: false,
+            pdf_output_path: String::new(),
+            // Heats of the same event get their own files
+            output_name_template: "{event_code}_{round}_{heat}".to_string(),
+            // What the output did before there was a choice
+            output_policy: 0,
+            auto_process_enabled: false,
+            auto_print_enabled: false,
+            auto_print_printer: String::new(),
+            auto_print_copies: 1,
+            progression_enabled: false,
+            advance_top_per_heat: 2,
+            advance_best_times: 0,
+            next_round_heat_size: 4,
+            seeding_method: 0,
+            draw_table: String::new(),
+            evt_output_enabled: false,
+            evt_output_path: String::new(),
+            mass_start_enabled: false,
+            sprint_points: "3,2,1".to_string(),
+            finish_points: "60,40,20,10,6,3".to_string(),
+            team_counting_skater: 3,
+            pdf_gap_to_leader: false,
+            pdf_gap_to_previous: false,
+            pdf_speed: false,
+            pdf_lap_average: false,
+            preview_resolution: 100,
+            track_type: 0,
+            records_path: String::new(),
+            registration_path: String::new(),
+            category_rules: String::new(),
+            club_points: "10,8,6,5,4,3,2,1".to_string(),
+        }
+    }
 }
 
 fn get_path() -> String {
@@ -20,43 +109,181 @@ fn get_path() -> String {
     )
 }
 
+// Written beside the file and renamed over it, so a reader on another thread never sees half of it
+pub fn write_atomic(path: &str, contents: &str) -> std::io::Result<()> {
+    let temp = format!("{}.tmp", path);
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+// Changes whenever any setting is saved, or the records or registrations file they point to is
+// replaced, so anything worked out from the settings can tell when it's out of date
+pub fn config_hash() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    std::fs::read(get_path()).unwrap_or_default().hash(&mut hasher);
+    let settings = load_config();
+    for path in [&settings.records_path, &settings.registration_path] {
+        std::fs::metadata(path.as_str())
+            .and_then(|x| x.modified())
+            .ok()
+            .hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
 pub fn save_config(data: SettingsData) {
     let config = SettingsDataAnalog {
         paths: data.search_paths.iter().map(|x| x.to_string()).collect(),
+        recursive_paths: data.recursive_paths.iter().collect(),
+        include_globs: data.include_globs.iter().map(|x| x.to_string()).collect(),
+        exclude_globs: data.exclude_globs.iter().map(|x| x.to_string()).collect(),
+        stability_interval_ms: data.stability_interval_ms,
         pdf_output_enabled: data.pdf_output_enabled,
         pdf_output_path: data.pdf_output_path.into(),
+        output_name_template: data.output_name_template.into(),
+        output_policy: data.output_policy,
+        auto_process_enabled: data.auto_process_enabled,
+        auto_print_enabled: data.auto_print_enabled,
+        auto_print_printer: data.auto_print_printer.into(),
+        auto_print_copies: data.auto_print_copies,
+        progression_enabled: data.progression_enabled,
+        advance_top_per_heat: data.advance_top_per_heat,
+        advance_best_times: data.advance_best_times,
+        next_round_heat_size: data.next_round_heat_size,
+        seeding_method: data.seeding_method,
+        draw_table: data.draw_table.into(),
+        evt_output_enabled: data.evt_output_enabled,
+        evt_output_path: data.evt_output_path.into(),
+        mass_start_enabled: data.mass_start_enabled,
+        sprint_points: data.sprint_points.into(),
+        finish_points: data.finish_points.into(),
+        team_counting_skater: data.team_counting_skater,
+        pdf_gap_to_leader: data.pdf_gap_to_leader,
+        pdf_gap_to_previous: data.pdf_gap_to_previous,
+        pdf_speed: data.pdf_speed,
+        pdf_lap_average: data.pdf_lap_average,
+        preview_resolution: data.preview_resolution,
+        track_type: data.track_type,
+        records_path: data.records_path.into(),
+        registration_path: data.registration_path.into(),
+        category_rules: data.category_rules.into(),
+        club_points: data.club_points.into(),
     };
 
     let json = serde_json::to_string(&config)
         .expect("Failed to parse config. This is an internal error and should be reported.");
 
     let path = get_path();
-    std::fs::write(&path, json).expect(&format!("Error writing to {}", path));
+    if let Err(e) = write_atomic(&path, &json) {
+        report_error(format!("Error writing to {}, {}", path, e));
+        return;
+    }
+    *LAST_GOOD.lock().unwrap() = Some(json);
+}
+
+fn read_config(path: &str) -> Result<SettingsDataAnalog, String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let analog = serde_json::from_str(&raw).map_err(|e| e.to_string())?;
+    *LAST_GOOD.lock().unwrap() = Some(raw);
+
+    Ok(analog)
 }
 
-pub fn load_config() -> Option<SettingsData> {
+// A config file that can't be read is reported and copied aside before anything is saved over
+// it, and the last settings that could be read stand in for it
+pub fn load_config() -> SettingsData {
     let path = get_path();
 
     let exists = std::fs::exists(&path).unwrap_or_else(|_| false);
 
-    if exists {
-        let analog: SettingsDataAnalog = serde_json::from_str(
-            &std::fs::read_to_string(path).expect("Failed to read config file"),
-        )
-        .unwrap();
-
-        Some(SettingsData {
-            pdf_output_enabled: analog.pdf_output_enabled,
-            pdf_output_path: analog.pdf_output_path.into(),
-            search_paths: ModelRc::new(VecModel::from(
-                analog
-                    .paths
-                    .iter()
-                    .map(|x| SharedString::from(x))
-                    .collect::<Vec<_>>(),
-            )),
-        })
+    let analog = if exists {
+        match read_config(&path) {
+            Ok(x) => x,
+            Err(e) => {
+                let backup = format!("{}.bad", path);
+                let _ = std::fs::copy(&path, &backup);
+                report_error(format!(
+                    "Failed to read settings from {}, {}. A copy was kept at {}",
+                    path, e, backup
+                ));
+                LAST_GOOD
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .and_then(|x| serde_json::from_str(x).ok())
+                    .unwrap_or_default()
+            }
+        }
     } else {
-        Some(SettingsData::default())
+        SettingsDataAnalog::default()
+    };
+
+    SettingsData {
+        pdf_output_enabled: analog.pdf_output_enabled,
+        pdf_output_path: analog.pdf_output_path.into(),
+        output_name_template: analog.output_name_template.into(),
+        output_policy: analog.output_policy,
+        auto_process_enabled: analog.auto_process_enabled,
+        auto_print_enabled: analog.auto_print_enabled,
+        auto_print_printer: analog.auto_print_printer.into(),
+        auto_print_copies: analog.auto_print_copies,
+        search_paths: ModelRc::new(VecModel::from(
+            analog
+                .paths
+                .iter()
+                .map(|x| SharedString::from(x))
+                .collect::<Vec<_>>(),
+        )),
+        // Older configs have no recursive flags or filters, those paths stay top level only
+        recursive_paths: ModelRc::new(VecModel::from(
+            (0..analog.paths.len())
+                .map(|i| analog.recursive_paths.get(i).copied().unwrap_or(false))
+                .collect::<Vec<_>>(),
+        )),
+        // and paths from before the filters only list LIF files
+        include_globs: ModelRc::new(VecModel::from(
+            (0..analog.paths.len())
+                .map(|i| {
+                    SharedString::from(
+                        analog
+                            .include_globs
+                            .get(i)
+                            .map(|x| x.as_str())
+                            .unwrap_or(DEFAULT_INCLUDE_GLOBS),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )),
+        exclude_globs: ModelRc::new(VecModel::from(
+            (0..analog.paths.len())
+                .map(|i| {
+                    SharedString::from(analog.exclude_globs.get(i).cloned().unwrap_or_default())
+                })
+                .collect::<Vec<_>>(),
+        )),
+        stability_interval_ms: analog.stability_interval_ms,
+        progression_enabled: analog.progression_enabled,
+        advance_top_per_heat: analog.advance_top_per_heat,
+        advance_best_times: analog.advance_best_times,
+        next_round_heat_size: analog.next_round_heat_size,
+        seeding_method: analog.seeding_method,
+        draw_table: analog.draw_table.into(),
+        evt_output_enabled: analog.evt_output_enabled,
+        evt_output_path: analog.evt_output_path.into(),
+        mass_start_enabled: analog.mass_start_enabled,
+        sprint_points: analog.sprint_points.into(),
+        finish_points: analog.finish_points.into(),
+        team_counting_skater: analog.team_counting_skater,
+        pdf_gap_to_leader: analog.pdf_gap_to_leader,
+        pdf_gap_to_previous: analog.pdf_gap_to_previous,
+        pdf_speed: analog.pdf_speed,
+        pdf_lap_average: analog.pdf_lap_average,
+        preview_resolution: analog.preview_resolution,
+        track_type: analog.track_type,
+        records_path: analog.records_path.into(),
+        registration_path: analog.registration_path.into(),
+        category_rules: analog.category_rules.into(),
+        club_points: analog.club_points.into(),
     }
 }
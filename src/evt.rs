@@ -0,0 +1,275 @@
+use crate::parse::{CompetitorRow, EventRow};
+use std::path::{Path, PathBuf};
+
+// A line of lynx.evt, kept byte for byte with its line ending so lines that aren't being replaced
+// are written back exactly as FinishLynx or the operator left them
+struct EvtLine {
+    raw: Vec<u8>,
+    kind: EvtLineKind,
+}
+
+enum EvtLineKind {
+    // Starts a heat, followed by one competitor line per skater
+    Event {
+        event_code: String,
+        round: Option<u8>,
+    },
+    Competitor,
+    Other,
+}
+
+fn read_evt(path: &Path) -> Result<Vec<EvtLine>, String> {
+    let raw = match std::fs::read(path) {
+        Ok(x) => x,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(format!("Failed to read {}, {}", path.display(), e)),
+    };
+
+    let mut lines = vec![];
+    for line in raw.split_inclusive(|x| *x == b'\n') {
+        let text = String::from_utf8_lossy(line);
+        let text = text.trim_end_matches(['\r', '\n']);
+
+        // Competitor lines start with an empty field, event lines start with the event number
+        let kind = if text.trim().is_empty() {
+            EvtLineKind::Other
+        } else if text.starts_with(",") {
+            EvtLineKind::Competitor
+        } else {
+            let split = text.split(",").collect::<Vec<_>>();
+            EvtLineKind::Event {
+                event_code: split.first().unwrap_or(&"").to_string(),
+                round: split.get(1).and_then(|x| x.trim().parse::<u8>().ok()),
+            }
+        };
+        lines.push(EvtLine {
+            raw: line.to_vec(),
+            kind,
+        });
+    }
+
+    Ok(lines)
+}
+
+fn is_heat_of(line: &EvtLine, code: &str, of_round: Option<u8>) -> bool {
+    match &line.kind {
+        EvtLineKind::Event { event_code, round } => event_code == code && *round == of_round,
+        _ => false,
+    }
+}
+
+fn evt_field(field: &str) -> String {
+    if field.contains(",") {
+        format!("\"{}\"", field.replace("\"", ""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Number of heats of a round the operator has scheduled in lynx.evt, if the round is listed
+pub fn scheduled_heats(path: &Path, event_code: &str, round: Option<u8>) -> Option<usize> {
+    let count = read_evt(path)
+        .ok()?
+        .iter()
+        .filter(|x| is_heat_of(x, event_code, round))
+        .count();
+
+    if count > 0 { Some(count) } else { None }
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("evt.bak")
+}
+
+// Replaces the heats of the round after `event` in lynx.evt with `heats`. Every other line is
+// kept as it was, and the file as it was before is copied to lynx.evt.bak first.
+pub fn write_next_round(
+    path: &Path,
+    event: &EventRow,
+    heats: &Vec<Vec<CompetitorRow>>,
+) -> Result<(), String> {
+    let next_round = event.round.unwrap_or(1).saturating_add(1);
+    let lines = read_evt(path)?;
+
+    // New lines follow the file's own line endings
+    let ending = match lines.first() {
+        Some(x) if !x.raw.ends_with(b"\r\n") && x.raw.ends_with(b"\n") => "\n",
+        _ => "\r\n",
+    };
+
+    let mut new_lines = vec![];
+    for (i, heat) in heats.iter().enumerate() {
+        new_lines.push(format!(
+            "{},{},{},{}",
+            event.event_code,
+            next_round,
+            i + 1,
+            evt_field(&event.event_name)
+        ));
+        for competitor in heat {
+            new_lines.push(format!(
+                ",{},{},{},{},{}",
+                competitor
+                    .skater_id
+                    .filter(|x| *x != i32::MAX as u32)
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+                competitor.lane.unwrap_or(0),
+                evt_field(&competitor.last_name),
+                evt_field(&competitor.first_name),
+                evt_field(&competitor.club)
+            ));
+        }
+    }
+    let mut new_heats = vec![];
+    for line in new_lines {
+        new_heats.extend_from_slice(line.as_bytes());
+        new_heats.extend_from_slice(ending.as_bytes());
+    }
+
+    // The new heats go where the old ones were, or at the end if the round wasn't listed
+    let mut out: Vec<u8> = vec![];
+    let mut replacing = false;
+    let mut inserted = false;
+    for line in &lines {
+        if is_heat_of(line, &event.event_code, Some(next_round)) {
+            replacing = true;
+            if !inserted {
+                out.extend_from_slice(&new_heats);
+                inserted = true;
+            }
+            continue;
+        }
+        if replacing && matches!(line.kind, EvtLineKind::Competitor) {
+            continue;
+        }
+        replacing = false;
+        out.extend_from_slice(&line.raw);
+    }
+    if !inserted {
+        if out.len() > 0 && !out.ends_with(b"\n") {
+            out.extend_from_slice(ending.as_bytes());
+        }
+        out.extend_from_slice(&new_heats);
+    }
+
+    if let Some(parent) = path.parent() {
+        if !std::fs::exists(parent).unwrap_or(false) {
+            let _ = std::fs::create_dir_all(parent);
+        }
+    }
+    if std::fs::exists(path).unwrap_or(false) {
+        let backup = backup_path(path);
+        std::fs::copy(path, &backup).map_err(|e| {
+            format!("Error backing up {} to {}, {}", path.display(), backup.display(), e)
+        })?;
+    }
+
+    let temp = path.with_extension("evt.tmp");
+    std::fs::write(&temp, out)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| format!("Error writing to {}, {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A lynx.evt in a folder of its own, removed again by the test
+    fn evt_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lifpdf_evt_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lynx.evt");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn event() -> EventRow {
+        EventRow {
+            event_code: "12".to_string(),
+            round: Some(1),
+            event_name: "500m Open, Men".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn skater(id: u32, lane: u8, last_name: &str) -> CompetitorRow {
+        CompetitorRow {
+            skater_id: Some(id),
+            lane: Some(lane),
+            last_name: last_name.to_string(),
+            first_name: "Sam".to_string(),
+            club: "Eagles".to_string(),
+            ..Default::default()
+        }
+    }
+
+    const SCHEDULE: &str = "12,1,1,500m\r\n,1,1,Smith,Sam,Eagles\r\n,2,2,Jones,Sam,Eagles\r\n\
+        12,1,2,500m\r\n,3,1,Brown,Sam,Eagles\r\n13,1,1,1000m\r\n,4,1,Green,Sam,Eagles\r\n";
+
+    #[test]
+    fn next_round_is_appended_and_heats_are_kept() {
+        let path = evt_file("append", SCHEDULE);
+        assert_eq!(scheduled_heats(&path, "12", Some(1)), Some(2));
+        assert_eq!(scheduled_heats(&path, "12", Some(2)), None);
+
+        let heats = vec![vec![skater(1, 1, "Smith"), skater(3, 2, "Brown")]];
+        write_next_round(&path, &event(), &heats).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            written,
+            format!(
+                "{}12,2,1,\"500m Open, Men\"\r\n,1,1,Smith,Sam,Eagles\r\n,3,2,Brown,Sam,Eagles\r\n",
+                SCHEDULE
+            )
+        );
+        assert_eq!(scheduled_heats(&path, "12", Some(1)), Some(2));
+        assert_eq!(scheduled_heats(&path, "12", Some(2)), Some(1));
+        assert_eq!(
+            std::fs::read_to_string(backup_path(&path)).unwrap(),
+            SCHEDULE
+        );
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn scheduled_round_is_replaced_in_place() {
+        let path = evt_file(
+            "replace",
+            "12,2,1,500m\n,9,1,Old,Sam,Eagles\n12,2,2,500m\n,8,1,Old,Sam,Eagles\n\
+             13,1,1,1000m\n,4,1,Green,Sam,Eagles\n",
+        );
+
+        let heats = vec![vec![skater(1, 1, "Smith")], vec![skater(2, 1, "Jones")]];
+        write_next_round(&path, &event(), &heats).unwrap();
+
+        // Unix line endings stay as they were
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "12,2,1,\"500m Open, Men\"\n,1,1,Smith,Sam,Eagles\n\
+             12,2,2,\"500m Open, Men\"\n,2,1,Jones,Sam,Eagles\n\
+             13,1,1,1000m\n,4,1,Green,Sam,Eagles\n"
+        );
+        assert!(std::fs::exists(backup_path(&path)).unwrap());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn missing_file_is_created_without_backup() {
+        let path = evt_file("create", "");
+        std::fs::remove_file(&path).unwrap();
+
+        write_next_round(&path, &event(), &vec![vec![skater(1, 1, "Smith")]]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "12,2,1,\"500m Open, Men\"\r\n,1,1,Smith,Sam,Eagles\r\n"
+        );
+        assert!(!std::fs::exists(backup_path(&path)).unwrap());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -11,3 +11,10 @@ pub fn is_time_discrepancy(pf_time: SkaterTime, splits: &Vec<SkaterTime>) -> boo
 
     lowest_difference > 0.4 && splits.len() > 0
 }
+
+pub fn is_lap_count_mismatch(splits: &Vec<SkaterTime>, expected_laps: Option<u32>) -> bool {
+    match expected_laps {
+        Some(laps) => splits.len() > 0 && splits.len() != laps as usize,
+        None => false,
+    }
+}
@@ -0,0 +1,365 @@
+use crate::config::write_atomic;
+use crate::parse::RaceEvent;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BestMark {
+    #[default]
+    None,
+    PersonalBest,
+    SeasonBest,
+}
+
+impl BestMark {
+    pub fn mark(&self) -> &'static str {
+        match self {
+            BestMark::None => "",
+            BestMark::PersonalBest => "PB",
+            BestMark::SeasonBest => "SB",
+        }
+    }
+
+    pub fn from_mark(mark: &str) -> Self {
+        match mark {
+            "PB" => BestMark::PersonalBest,
+            "SB" => BestMark::SeasonBest,
+            _ => BestMark::None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct HistoryEntry {
+    skater_id: u32,
+    distance_m: f32,
+    time_secs: f32,
+    // Seconds since the epoch the race was run, taken from the LIF file
+    recorded: u64,
+    source: String,
+}
+
+fn get_path() -> String {
+    #[cfg(target_os = "windows")]
+    let slash = '\\';
+    #[cfg(not(target_os = "windows"))]
+    let slash = '/';
+    format!(
+        "{}{}lifpdf_history.json",
+        dirs::config_dir().unwrap().to_str().unwrap(),
+        slash
+    )
+}
+
+// Held from reading the history to writing it back, the preview and the auto-process worker can
+// both be recording a race at once
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+// A history that can't be read is left alone rather than replaced with an empty one
+fn load_history() -> Result<Vec<HistoryEntry>, String> {
+    let path = get_path();
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => serde_json::from_str(&raw)
+            .map_err(|e| format!("Failed to read results history {}, {}", path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(format!("Failed to read results history {}, {}", path, e)),
+    }
+}
+
+fn save_history(history: &Vec<HistoryEntry>) -> Result<(), String> {
+    let json = serde_json::to_string(history)
+        .expect("Failed to serialize history. This is an internal error and should be reported.");
+
+    let path = get_path();
+    write_atomic(&path, &json).map_err(|e| format!("Error writing to {}, {}", path, e))
+}
+
+// Year, month and day in UTC, see http://howardhinnant.github.io/date_algorithms.html
+pub fn civil_date(unix_secs: u64) -> (i64, i64, i64) {
+    let z = (unix_secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+// When the race was run. LIF files only hold the time of day, so this is the day the file was
+// written.
+pub fn race_date(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_secs())
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+// Seasons run July to June and are named by the year they start in
+pub fn season_of(unix_secs: u64) -> i64 {
+    let (year, month, _) = civil_date(unix_secs);
+
+    if month >= 7 { year } else { year - 1 }
+}
+
+// Records every timed skater of the race in the history store, and marks times that beat every
+// earlier result at the distance as a PB, or an SB if they only beat this season's results.
+// Skaters without earlier results aren't marked.
+pub fn record_and_mark(event: &mut RaceEvent, path: &Path) -> Result<(), String> {
+    let distance_m = match event.event.distance_m {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    let source = path.to_string_lossy().to_string();
+    let recorded = race_date(path).unwrap_or_else(now_secs);
+
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut history = load_history()?;
+    if mark_and_record(&mut history, event, distance_m, &source, recorded) {
+        save_history(&history)?;
+    }
+
+    Ok(())
+}
+
+// Marks the race against `history` and adds or updates its results there, returning whether
+// anything in the history changed
+fn mark_and_record(
+    history: &mut Vec<HistoryEntry>,
+    event: &mut RaceEvent,
+    distance_m: f32,
+    source: &str,
+    recorded: u64,
+) -> bool {
+    let mut changed = false;
+    for competitor in event.competitors.iter_mut() {
+        competitor.best_mark = BestMark::None;
+
+        let skater_id = match competitor.skater_id {
+            Some(x) if x != i32::MAX as u32 => x,
+            _ => continue,
+        };
+        let time_secs = match competitor.time {
+            Some(t) if !t.is_no_time() => t.total_secs(),
+            _ => continue,
+        };
+
+        let earlier = history
+            .iter()
+            .filter(|x| {
+                x.skater_id == skater_id
+                    && x.distance_m == distance_m
+                    && x.source != source
+                    && x.recorded < recorded
+            })
+            .collect::<Vec<_>>();
+
+        if earlier.len() > 0 {
+            let best = earlier.iter().map(|x| x.time_secs).fold(f32::MAX, f32::min);
+            let season_best = earlier
+                .iter()
+                .filter(|x| season_of(x.recorded) == season_of(recorded))
+                .map(|x| x.time_secs)
+                .fold(f32::MAX, f32::min);
+
+            if time_secs < best {
+                competitor.best_mark = BestMark::PersonalBest;
+            } else if time_secs < season_best {
+                competitor.best_mark = BestMark::SeasonBest;
+            }
+        }
+
+        match history
+            .iter_mut()
+            .find(|x| x.skater_id == skater_id && x.distance_m == distance_m && x.source == source)
+        {
+            Some(entry) => {
+                if entry.time_secs != time_secs || entry.recorded != recorded {
+                    entry.time_secs = time_secs;
+                    entry.recorded = recorded;
+                    changed = true;
+                }
+            }
+            None => {
+                history.push(HistoryEntry {
+                    skater_id,
+                    distance_m,
+                    time_secs,
+                    recorded,
+                    source: source.to_string(),
+                });
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{CompetitorRow, SkaterTime};
+
+    const DAY: u64 = 86400;
+    // 2024-03-01, in the 2023 season
+    const MARCH: u64 = 1709251200;
+
+    fn race(times: &[(u32, f32)]) -> RaceEvent {
+        RaceEvent {
+            competitors: times
+                .iter()
+                .map(|x| CompetitorRow {
+                    skater_id: Some(x.0),
+                    time: Some(SkaterTime::from_secs(x.1)),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn entry(skater_id: u32, time_secs: f32, recorded: u64, source: &str) -> HistoryEntry {
+        HistoryEntry {
+            skater_id,
+            distance_m: 500.0,
+            time_secs,
+            recorded,
+            source: source.to_string(),
+        }
+    }
+
+    fn marks(event: &RaceEvent) -> Vec<&'static str> {
+        event
+            .competitors
+            .iter()
+            .map(|x| x.best_mark.mark())
+            .collect()
+    }
+
+    #[test]
+    fn first_result_is_recorded_without_a_mark() {
+        let mut history = vec![];
+        let mut event = race(&[(1, 45.0)]);
+        assert!(mark_and_record(
+            &mut history,
+            &mut event,
+            500.0,
+            "b.lif",
+            MARCH
+        ));
+
+        assert_eq!(marks(&event), vec![""]);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].time_secs, 45.0);
+        assert_eq!(history[0].recorded, MARCH);
+    }
+
+    #[test]
+    fn improved_result_is_a_personal_best() {
+        let mut history = vec![entry(1, 46.0, MARCH - 30 * DAY, "a.lif")];
+        let mut event = race(&[(1, 45.0)]);
+        mark_and_record(&mut history, &mut event, 500.0, "b.lif", MARCH);
+
+        assert_eq!(marks(&event), vec!["PB"]);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn slower_result_is_not_marked() {
+        let mut history = vec![entry(1, 44.0, MARCH - 30 * DAY, "a.lif")];
+        let mut event = race(&[(1, 45.0)]);
+        mark_and_record(&mut history, &mut event, 500.0, "b.lif", MARCH);
+
+        assert_eq!(marks(&event), vec![""]);
+    }
+
+    #[test]
+    fn best_of_the_season_is_a_season_best() {
+        // The PB was last season, the only result this season was slower
+        let mut history = vec![
+            entry(1, 43.0, MARCH - 365 * DAY, "a.lif"),
+            entry(1, 46.0, MARCH - 30 * DAY, "b.lif"),
+        ];
+        let mut event = race(&[(1, 45.0), (2, 45.0)]);
+        mark_and_record(&mut history, &mut event, 500.0, "c.lif", MARCH);
+        assert_eq!(marks(&event), vec!["SB", ""]);
+
+        // The first race of a new season is its best so far
+        let mut event = race(&[(1, 44.0)]);
+        mark_and_record(&mut history, &mut event, 500.0, "d.lif", MARCH + 180 * DAY);
+        assert_eq!(marks(&event), vec!["SB"]);
+    }
+
+    #[test]
+    fn same_race_is_not_marked_against_itself_or_later_races() {
+        let mut history = vec![
+            entry(1, 46.0, MARCH, "b.lif"),
+            entry(1, 40.0, MARCH + DAY, "c.lif"),
+        ];
+        let mut event = race(&[(1, 45.0)]);
+        assert!(mark_and_record(
+            &mut history,
+            &mut event,
+            500.0,
+            "b.lif",
+            MARCH
+        ));
+
+        // Its own entry and later races don't count, so there's nothing to beat
+        assert_eq!(marks(&event), vec![""]);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].time_secs, 45.0);
+
+        let mut event = race(&[(1, 45.0)]);
+        assert!(!mark_and_record(
+            &mut history,
+            &mut event,
+            500.0,
+            "b.lif",
+            MARCH
+        ));
+    }
+
+    #[test]
+    fn civil_date_handles_leap_years() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        // 2024 is divisible by 4
+        assert_eq!(civil_date(1709164800), (2024, 2, 29));
+        assert_eq!(civil_date(1709164800 + DAY), (2024, 3, 1));
+        // 2000 is divisible by 400
+        assert_eq!(civil_date(951782400), (2000, 2, 29));
+        assert_eq!(civil_date(951868800), (2000, 3, 1));
+        // 2100 is divisible by 100 but not 400
+        assert_eq!(civil_date(4107542400 - DAY), (2100, 2, 28));
+        assert_eq!(civil_date(4107542400), (2100, 3, 1));
+    }
+
+    #[test]
+    fn civil_date_rolls_over_at_midnight() {
+        assert_eq!(civil_date(1703980800 - 1), (2023, 12, 30));
+        assert_eq!(civil_date(1703980800 + DAY - 1), (2023, 12, 31));
+        assert_eq!(civil_date(1703980800 + DAY), (2024, 1, 1));
+    }
+
+    #[test]
+    fn season_starts_in_july() {
+        assert_eq!(season_of(1719792000 - 1), 2023);
+        assert_eq!(season_of(1719792000), 2024);
+        assert_eq!(season_of(1709164800), 2023);
+        assert_eq!(season_of(1703980800 + DAY), 2023);
+    }
+}
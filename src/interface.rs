@@ -1,95 +1,544 @@
-use crate::config::{load_config, save_config};
-use crate::parse::{RaceEvent, cmp_slint_skater_time};
-use crate::pdf::{gen_timesheet_pdf, pdf_to_image};
-use crate::table_data::gen_table_row;
-use crate::{MainWindow, SettingsData, SlintCompetitorRow, SlintRaceEvent};
+use crate::auto_process::PROCESSING_QUEUE;
+use crate::categories::{apply_categories, load_registrations, parse_category_rules};
+use crate::config::{DEFAULT_INCLUDE_GLOBS, config_hash, load_config, save_config};
+use crate::evt::{backup_path, scheduled_heats, write_next_round};
+use crate::history::{now_secs, race_date, record_and_mark};
+use crate::mass_start::{
+    PointsTable, is_mass_start, parse_points, parse_sprints, score_mass_start, sprints_path,
+};
+use crate::parse::{
+    CompetitorRow, RaceEvent, TrackType, cmp_slint_metric, cmp_slint_skater_time,
+};
+use crate::meet::load_meet;
+use crate::meet_db::{
+    acknowledge_flags, flag_lap_count_mismatches, latest_revision, load_race, mark_pdf_generated,
+    mark_printed, output_file_owner, output_status, record_output_file, unacknowledged_flags,
+};
+use crate::output::{OutputPolicy, archive_existing, output_path, render_file_name};
+use crate::pdf::{
+    gen_club_standings_pdf, gen_records_report_pdf, gen_skater_report_pdf, gen_timesheet_pdf,
+    render_pdf,
+};
+use crate::print::{PRINT_SCALE, print_pdf_file};
+use crate::progression::{AdvancementRule, Seeding, progress_event};
+use crate::records::{BrokenRecord, Record, load_records, mark_records};
+use crate::render_cache::{RENDER_CACHE, RenderKey, RenderedRace};
+use crate::scan::{scan_lif_files, search_path_of};
+use crate::search::{fuzzy_match, race_search_text};
+use crate::stability::split_stable;
+use crate::standings::{club_standings, standings_csv};
+use crate::status::{report_error, set_status_window, show_error};
+use crate::lookup::find_skater;
+use crate::metrics::apply_metrics;
+use crate::table_data::{
+    MetricColumns, gen_appearance_row, gen_category_cell, gen_metrics_row,
+    gen_points_preview_rows, gen_queue_row, gen_table_row, gen_team_row,
+};
+use crate::team::{group_teams, is_team_event};
+use crate::{MainWindow, SettingsData, SlintCompetitorRow, SlintLifFile, SlintRaceEvent};
 use native_dialog::MessageLevel;
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use slint::{
     ComponentHandle, Model, ModelExt, ModelRc, SharedString, StandardListViewItem, VecModel, Weak,
 };
-use std::cell::RefCell;
-use std::fs::read_dir;
-use std::io::Read;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+// FinishLynx writing a file sets off a burst of events, so changes are collected until the
+// watched directories have been quiet for this long and then applied together
+const DEBOUNCE: Duration = Duration::from_millis(300);
+// How many times a file still being written is checked again before waiting for its next event
+const MAX_STABILITY_RETRIES: u32 = 20;
+
+struct PendingChanges {
+    paths: Vec<PathBuf>,
+    full_reload: bool,
+    last_event: Instant,
+    scheduled: bool,
+    retries: HashMap<PathBuf, u32>,
+}
+
+static PENDING_CHANGES: LazyLock<Mutex<PendingChanges>> = LazyLock::new(|| {
+    Mutex::new(PendingChanges {
+        paths: vec![],
+        full_reload: false,
+        last_event: Instant::now(),
+        scheduled: false,
+        retries: HashMap::new(),
+    })
+});
 
 fn watcher_fn(res: notify::Result<notify::Event>, main_window_weak: Weak<MainWindow>) {
     match res {
         Ok(event) => match event.kind {
-            // Reload paths for any event but access
+            // Update the list for any event but access
             EventKind::Access(_) => {}
-            _ => {
-                slint::invoke_from_event_loop(move || {
-                    if let Some(main_window) = main_window_weak.upgrade() {
-                        reload_lif_files(&main_window);
-                    };
-                })
-                    .expect("Failed to get Slint context in watcher closure");
-            }
+            _ => queue_changes(event.paths, main_window_weak),
         },
-        Err(e) => println!("Watch Error: {:?}", e),
+        Err(e) => report_error(format!("Watch error, {}", e)),
+    }
+}
+
+fn queue_changes(paths: Vec<PathBuf>, main_window_weak: Weak<MainWindow>) {
+    let mut pending = PENDING_CHANGES.lock().unwrap();
+    // Events without paths mean the watcher lost track, so everything is read again
+    if paths.is_empty() {
+        pending.full_reload = true;
+    }
+    for path in paths {
+        if !pending.paths.contains(&path) {
+            pending.paths.push(path);
+        }
+    }
+    pending.last_event = Instant::now();
+    if pending.scheduled {
+        return;
+    }
+    pending.scheduled = true;
+    drop(pending);
+
+    std::thread::spawn(move || {
+        loop {
+            let wait = (PENDING_CHANGES.lock().unwrap().last_event + DEBOUNCE)
+                .saturating_duration_since(Instant::now());
+            if wait.is_zero() {
+                break;
+            }
+            std::thread::sleep(wait);
+        }
+
+        let (paths, full_reload) = {
+            let mut pending = PENDING_CHANGES.lock().unwrap();
+            pending.scheduled = false;
+            (
+                std::mem::take(&mut pending.paths),
+                std::mem::take(&mut pending.full_reload),
+            )
+        };
+
+        // Nothing gets parsed until FinishLynx has finished writing it
+        let interval =
+            Duration::from_millis(load_config().stability_interval_ms.max(0) as u64);
+        let (paths, unstable) = split_stable(paths, interval);
+        retry_unstable(&paths, unstable, main_window_weak.clone());
+        if paths.is_empty() && !full_reload {
+            return;
+        }
+
+        // Any race changing can change the PBs, records and qualifiers shown on the others
+        RENDER_CACHE.lock().unwrap().clear();
+        let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+            if full_reload {
+                reload_lif_files(&main_window);
+            } else {
+                apply_file_changes(&main_window, paths);
+            }
+        });
+    });
+}
+
+fn retry_unstable(
+    stable: &Vec<PathBuf>,
+    unstable: Vec<PathBuf>,
+    main_window_weak: Weak<MainWindow>,
+) {
+    let mut retry = vec![];
+    {
+        let mut pending = PENDING_CHANGES.lock().unwrap();
+        for path in stable {
+            pending.retries.remove(path);
+        }
+        for path in unstable {
+            let count = pending.retries.entry(path.clone()).or_insert(0);
+            *count += 1;
+            if *count > MAX_STABILITY_RETRIES {
+                report_error(format!(
+                    "{} still hadn't finished writing after {} checks, it won't be updated until it changes again",
+                    path.display(),
+                    MAX_STABILITY_RETRIES
+                ));
+                pending.retries.remove(&path);
+            } else {
+                retry.push(path);
+            }
+        }
+    }
+
+    if !retry.is_empty() {
+        queue_changes(retry, main_window_weak);
     }
 }
 
+// What each race can be found by besides its file name, with the modification time of the file
+// it was read from. Filled in off the UI thread so filtering never has to parse a race.
+static SEARCH_INDEX: LazyLock<Mutex<HashMap<PathBuf, (SystemTime, String)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Match the filter against what's inside each race too, not just the file name. Races that
+// haven't been indexed yet only match on their name until they are.
+fn passes_filter(filter: &str, name: &str, path: &Path) -> bool {
+    filter.trim().is_empty()
+        || fuzzy_match(name, filter)
+        || SEARCH_INDEX
+            .lock()
+            .unwrap()
+            .get(path)
+            .is_some_and(|x| fuzzy_match(&x.1, filter))
+}
+
+// Indexes the races that changed since they were last indexed, then filters the list again if
+// that could let more of them through
+fn index_races(main_window: &MainWindow, paths: Vec<PathBuf>) {
+    let main_window_weak = main_window.as_weak();
+    std::thread::spawn(move || {
+        let mut indexed = false;
+        for path in paths {
+            let metadata = std::fs::metadata(&path).ok().filter(|x| x.is_file());
+            let modified = match metadata.and_then(|x| x.modified().ok()) {
+                Some(x) => x,
+                None => {
+                    SEARCH_INDEX.lock().unwrap().remove(&path);
+                    continue;
+                }
+            };
+            if SEARCH_INDEX
+                .lock()
+                .unwrap()
+                .get(&path)
+                .is_some_and(|x| x.0 == modified)
+            {
+                continue;
+            }
+
+            let name = file_name_of(&path);
+            let text = match load_race(&path) {
+                Ok(event) => race_search_text(&name, &event),
+                Err(_) => name,
+            };
+            SEARCH_INDEX.lock().unwrap().insert(path, (modified, text));
+            indexed = true;
+        }
+
+        if indexed {
+            let _ = main_window_weak.upgrade_in_event_loop(|main_window| {
+                if !main_window.get_lif_file_filter().trim().is_empty() {
+                    reload_lif_files(&main_window);
+                }
+            });
+        }
+    });
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
 fn reload_lif_files(main_window: &MainWindow) {
-    let settings = load_config().unwrap();
+    let settings = load_config();
     let search_paths = settings
         .search_paths
         .iter()
         .map(|x| x.to_string())
         .collect::<Vec<_>>();
     let mut files = vec![];
-    for search_path in search_paths {
-        for file in match read_dir(&search_path) {
-            Ok(x) => x.collect(),
-            Err(_) => vec![],
-        } {
-            if let Ok(file) = file {
-                let name = file.file_name().to_string_lossy().to_string();
-                if name
-                    .chars()
-                    .rev()
-                    .take(4)
-                    .collect::<String>()
-                    .to_lowercase()
-                    == "fil."
-                {
-                    if let Ok(metadata) = file.metadata() {
-                        let time = match metadata.modified() {
-                            Ok(t) => t,
-                            Err(_) => metadata
-                                .created()
-                                .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH),
-                        };
-                        files.push((name, time))
-                    }
-                }
-            }
+    for (path, group) in scan_lif_files(&settings) {
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            let time = match metadata.modified() {
+                Ok(t) => t,
+                Err(_) => metadata
+                    .created()
+                    .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH),
+            };
+            files.push((file_name_of(&path), time, path, group))
         }
     }
-    files.sort_by(|x, y| x.1.cmp(&y.1).reverse());
+    // Grouped by search path, newest first within each
+    files.sort_by(|x, y| x.3.cmp(&y.3).then(x.1.cmp(&y.1).reverse()));
+    index_races(main_window, files.iter().map(|x| x.2.clone()).collect());
 
     let filter = &main_window.get_lif_file_filter().to_string();
+    let entries = files
+        .iter()
+        .filter(|x| passes_filter(filter, &x.0, &x.2))
+        .map(|x| SlintLifFile {
+            file_name: x.0.as_str().into(),
+            path: x.2.to_string_lossy().to_string().into(),
+            group: search_paths[x.3].as_str().into(),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    let selected = selected_lif_path(main_window);
+    let model = Rc::new(VecModel::from(entries.clone()));
+    label_lif_files(&model);
+    main_window.set_lif_files(ModelRc::from(model));
+    restore_selection(main_window, selected);
+    update_most_recent(main_window);
+
+    load_lif_file_details(main_window, entries);
+}
+
+// Applies watcher events to the list one path at a time instead of reading every directory
+fn apply_file_changes(main_window: &MainWindow, paths: Vec<PathBuf>) {
+    let lif_files = main_window.get_lif_files();
+    let model = match lif_files.as_any().downcast_ref::<VecModel<SlintLifFile>>() {
+        Some(x) => x,
+        None => return reload_lif_files(main_window),
+    };
 
-    let lif_files = files
+    let settings = load_config();
+    let search_paths = settings
+        .search_paths
         .iter()
-        .filter(|x| x.0.contains(filter))
-        .map(|x| SharedString::from(&x.0))
+        .map(|x| x.to_string())
         .collect::<Vec<_>>();
-    main_window.set_lif_files(ModelRc::new(VecModel::from(lif_files)));
+    let filter = main_window.get_lif_file_filter().to_string();
+    let selected = selected_lif_path(main_window);
+    index_races(main_window, paths.clone());
+
+    let mut changed = vec![];
+    for path in paths {
+        // A new or renamed folder can hold any number of files
+        if path.is_dir() {
+            return reload_lif_files(main_window);
+        }
+
+        let key = SharedString::from(path.to_string_lossy().to_string());
+        let name = file_name_of(&path);
+        let existing = model.iter().position(|x| x.path == key);
+        let group = search_path_of(&settings, &path)
+            .filter(|_| path.is_file() && passes_filter(&filter, &name, &path));
+
+        match (existing, group) {
+            (Some(i), None) => {
+                model.remove(i);
+            }
+            (Some(i), Some(_)) => {
+                let entry = SlintLifFile {
+                    file_name: name.into(),
+                    path: key,
+                    group: model.row_data(i).unwrap_or_default().group,
+                    ..Default::default()
+                };
+                model.set_row_data(i, entry.clone());
+                changed.push(entry);
+            }
+            (None, Some(group)) => {
+                // A file that just appeared is the newest in its search path
+                let position = model
+                    .iter()
+                    .position(|x| {
+                        search_paths
+                            .iter()
+                            .position(|y| y.as_str() == x.group.as_str())
+                            .unwrap_or(usize::MAX)
+                            >= group
+                    })
+                    .unwrap_or(model.row_count());
+                let entry = SlintLifFile {
+                    file_name: name.into(),
+                    path: key,
+                    group: search_paths[group].as_str().into(),
+                    ..Default::default()
+                };
+                model.insert(position, entry.clone());
+                changed.push(entry);
+            }
+            (None, None) => {}
+        }
+    }
+
+    label_lif_files(model);
+    restore_selection(main_window, selected);
+    update_most_recent(main_window);
+
+    if settings.pdf_output_enabled && settings.auto_process_enabled {
+        queue_auto_process(
+            main_window,
+            changed
+                .iter()
+                .map(|x| PathBuf::from(x.path.as_str()))
+                .collect(),
+        );
+    }
+    load_lif_file_details(main_window, changed);
 }
 
-pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::PlatformError> {
-    main_window.set_race_event_set(false);
+// Shows the directory of files whose names collide, and a header above each search path
+fn label_lif_files(model: &VecModel<SlintLifFile>) {
+    let entries = model.iter().collect::<Vec<_>>();
+    for (i, entry) in entries.iter().enumerate() {
+        let collides = entries
+            .iter()
+            .any(|x| x.file_name == entry.file_name && x.path != entry.path);
+        let directory = if collides {
+            Path::new(entry.path.as_str())
+                .parent()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let first_in_group = i == 0 || entries[i - 1].group != entry.group;
 
-    // If config file exists, load it. Otherwise, do nothing
-    match load_config() {
-        Some(x) => {
-            main_window.set_settings_data(x);
+        if entry.directory.as_str() != directory || entry.first_in_group != first_in_group {
+            model.set_row_data(
+                i,
+                SlintLifFile {
+                    directory: directory.into(),
+                    first_in_group,
+                    ..entry.clone()
+                },
+            );
         }
-        None => {}
     }
+}
+
+fn selected_lif_path(main_window: &MainWindow) -> Option<SharedString> {
+    let selected = main_window.get_selected_lif_file();
+    if selected < 0 {
+        return None;
+    }
+    main_window
+        .get_lif_files()
+        .row_data(selected as usize)
+        .map(|x| x.path)
+}
+
+// Keeps the same file selected after rows are added or removed above it
+fn restore_selection(main_window: &MainWindow, selected: Option<SharedString>) {
+    let index = selected
+        .and_then(|path| {
+            main_window
+                .get_lif_files()
+                .iter()
+                .position(|x| x.path == path)
+        })
+        .map(|x| x as i32)
+        .unwrap_or(-1);
+    main_window.set_selected_lif_file(index);
+}
+
+fn update_most_recent(main_window: &MainWindow) {
+    let most_recent = main_window
+        .get_lif_files()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| {
+            std::fs::metadata(x.path.as_str())
+                .and_then(|x| x.modified())
+                .ok()
+                .map(|time| (i, time))
+        })
+        .max_by_key(|x| x.1)
+        .map(|x| x.0 as i32)
+        .unwrap_or(-1);
+    main_window.set_most_recent_lif_file(most_recent);
+}
+
+// Reading every race can take a while on a big meet, so the list shows the file names straight
+// away and the details fill in as each race is read
+fn load_lif_file_details(main_window: &MainWindow, entries: Vec<SlintLifFile>) {
+    if entries.is_empty() {
+        return;
+    }
+
+    let main_window_weak = main_window.as_weak();
+    std::thread::spawn(move || {
+        for entry in entries {
+            let entry = lif_file_details(entry);
+            let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+                set_lif_file_entry(&main_window, entry);
+            });
+        }
+    });
+}
+
+fn lif_file_details(entry: SlintLifFile) -> SlintLifFile {
+    let path = PathBuf::from(entry.path.as_str());
+    let (pdf_generated, printed) = output_status(&path);
+    match load_race(&path) {
+        Ok(event) => {
+            let round_heat = match (event.event.round, event.event.heat) {
+                (Some(round), Some(heat)) => format!("Round {} Heat {}", round, heat),
+                (Some(round), None) => format!("Round {}", round),
+                (None, Some(heat)) => format!("Heat {}", heat),
+                (None, None) => String::new(),
+            };
+            let finishers = event
+                .competitors
+                .iter()
+                .filter(|x| match x.time {
+                    Some(t) => !t.is_no_time() && x.place.unwrap_or(255) != 255,
+                    None => false,
+                })
+                .count();
+
+            SlintLifFile {
+                loaded: true,
+                event_name: event.event.event_name.into(),
+                round_heat: round_heat.into(),
+                finishers: finishers as i32,
+                start_time: event.event.start_time.into(),
+                validation: if unacknowledged_flags(&path) > 0 { 2 } else { 1 },
+                pdf_generated,
+                printed,
+                ..entry
+            }
+        }
+        Err(e) => {
+            report_error(format!("Failed to parse {}, {}", path.display(), e));
+            SlintLifFile {
+                loaded: true,
+                validation: 2,
+                pdf_generated,
+                printed,
+                ..entry
+            }
+        }
+    }
+}
+
+fn set_lif_file_entry(main_window: &MainWindow, entry: SlintLifFile) {
+    let lif_files = main_window.get_lif_files();
+    if let Some(i) = lif_files.iter().position(|x| x.path == entry.path) {
+        // The labels may have changed while the details were being read
+        let current = lif_files.row_data(i).unwrap_or_default();
+        lif_files.set_row_data(
+            i,
+            SlintLifFile {
+                directory: current.directory,
+                group: current.group,
+                first_in_group: current.first_in_group,
+                ..entry
+            },
+        );
+    }
+}
+
+// Re-reads one list entry after its flags or outputs change
+fn refresh_lif_file_entry(main_window: &MainWindow, path: &Path) {
+    let key = path.to_string_lossy().to_string();
+    if let Some(entry) = main_window
+        .get_lif_files()
+        .iter()
+        .find(|x| x.path.as_str() == key)
+    {
+        set_lif_file_entry(main_window, lif_file_details(entry));
+    }
+}
+
+pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::PlatformError> {
+    main_window.set_race_event_set(false);
+    set_status_window(main_window);
+
+    main_window.set_settings_data(load_config());
 
     // Watch paths for changes
     let main_window_weak = main_window.as_weak();
@@ -136,7 +585,7 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
         let main_window_weak = main_window.as_weak();
         main_window.on_settings_button_clicked(move || {
             if let Some(main_window) = main_window_weak.upgrade() {
-                let config = load_config().unwrap();
+                let config = load_config();
                 main_window.set_settings_data(config);
             }
         })
@@ -148,121 +597,110 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
         main_window.on_general_settings_update(move |settings_data| {
             if let Some(main_window) = main_window_weak.upgrade() {
                 // Reload lif files
-                let paths: &mut Vec<String> = &mut settings_data
-                    .search_paths
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect();
-                push_settings_data(paths, settings_data, &main_window);
+                let search_paths = SearchPaths::of(&settings_data);
+                push_settings_data(search_paths, settings_data, &main_window);
                 reload_lif_files(&main_window);
             }
         });
     }
 
-    let pub_pdf_document = Rc::new(RefCell::new(None));
-    let pub_pdf_bitmap = Rc::new(RefCell::new(None));
-    let cur_path = Rc::new(RefCell::new(None));
+    // Shared with the preview worker, which fills them in once the race is rendered
+    let cur_render: Arc<Mutex<Option<Arc<RenderedRace>>>> = Arc::new(Mutex::new(None));
+    let cur_path: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
     {
         let main_window_weak = main_window.as_weak();
-        let cur_path_clone = cur_path.clone();
-        let pub_pdf_doc_clone = pub_pdf_document.clone();
-        let pub_pdf_img_clone = pub_pdf_bitmap.clone();
+        let preview = Preview {
+            cur_path: cur_path.clone(),
+            cur_render: cur_render.clone(),
+        };
         main_window.on_table_changed(move || {
             if let Some(main_window) = main_window_weak.upgrade() {
                 let selected_lif = main_window.get_selected_lif_file();
-                let lif_files = main_window
-                    .get_lif_files()
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<_>>();
-                if selected_lif >= 0 && lif_files.len() > 0 {
-                    // I don't think there will be any situation where it is needed to disambiguate
-                    // between identically name files between directories, so I'm implementing it
-                    // like this. Can change later if necessary
-                    let event_name = lif_files.get(selected_lif as usize).unwrap();
-                    let mut event_path = None;
-
-                    for search_path in load_config().unwrap().search_paths.iter() {
-                        let search_path = search_path.to_string();
-                        match read_dir(search_path) {
-                            Ok(r) => {
-                                for file in r {
-                                    if let Ok(file) = file {
-                                        if file.file_name().to_string_lossy().to_string() == *event_name {
-                                            event_path = Some(file.path());
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            Err(_) => {}
-                        }
-                    }
+                let lif_files = main_window.get_lif_files();
+                if selected_lif >= 0 && lif_files.row_count() > 0 {
+                    let entry = lif_files
+                        .row_data(selected_lif as usize)
+                        .unwrap_or_default();
+                    let event_path = PathBuf::from(entry.path.as_str());
 
-                    match event_path {
-                        Some(e) => {
-                            let mut f = std::fs::File::open(&e).expect("Failed to open file");
-                            let mut buffer = Vec::new();
-                            f.read_to_end(&mut buffer)
-                                .expect("Failed to read bytes of file");
-
-                            let mut file_contents = String::new();
-                            for byte in buffer {
-                                file_contents.push(byte as char);
-                            }
-
-                            match RaceEvent::parse_lif(file_contents, event_name.clone()) {
-                                Ok(event) => {
-                                    let event: SlintRaceEvent = event.into();
-                                    main_window.set_event(event.event);
-                                    main_window.set_table_data(
-                                        gen_sorted_table(&main_window, &event.competitors).into(),
-                                    );
-                                    main_window.set_race_event_set(true);
-                                    let mut cur_path = cur_path_clone.borrow_mut();
-                                    *cur_path = Some(e.to_string_lossy().to_string());
-                                }
-                                Err(e) => {
-                                    let _ = native_dialog::DialogBuilder::message()
-                                        .set_level(MessageLevel::Error)
-                                        .set_title("Error parsing file")
-                                        .set_text(format!("Failed to parse {}, {}", event_name, e))
-                                        .alert()
-                                        .show();
-                                }
-                            };
-                        }
-                        None => {
-                            println!("No file called {} found", event_name);
-                        }
+                    if std::fs::exists(&event_path).unwrap_or(false) {
+                        start_preview_job(&main_window, event_path, preview.clone());
+                    } else {
+                        report_error(format!("No file called {} found", entry.file_name));
                     }
                 }
-
-                generate_display_pdf(
-                    &cur_path_clone,
-                    &pub_pdf_img_clone.clone(),
-                    &pub_pdf_doc_clone,
-                    main_window,
-                );
+            }
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        let preview = Preview {
+            cur_path: cur_path.clone(),
+            cur_render: cur_render.clone(),
+        };
+        main_window.on_preview_width_changed(move |width| {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let width = (width * main_window.window().scale_factor()).max(0.0) as u32;
+                PREVIEW_WIDTH.store(width, Ordering::SeqCst);
+                start_zoom_job(&main_window, preview.clone());
             }
         });
     }
 
     // Settings menu stuff
+    // The search paths, and what's set for each of them
+    struct SearchPaths {
+        paths: Vec<String>,
+        recursive: Vec<bool>,
+        include: Vec<String>,
+        exclude: Vec<String>,
+    }
+    impl SearchPaths {
+        fn of(settings_data: &SettingsData) -> Self {
+            let strings = |x: &ModelRc<SharedString>| -> Vec<String> {
+                x.iter().map(|x| x.to_string()).collect()
+            };
+            Self {
+                paths: strings(&settings_data.search_paths),
+                recursive: settings_data.recursive_paths.iter().collect(),
+                include: strings(&settings_data.include_globs),
+                exclude: strings(&settings_data.exclude_globs),
+            }
+        }
+
+        fn remove(&mut self, i: usize) {
+            self.paths.remove(i);
+            for list in [&mut self.include, &mut self.exclude] {
+                if i < list.len() {
+                    list.remove(i);
+                }
+            }
+            if i < self.recursive.len() {
+                self.recursive.remove(i);
+            }
+        }
+    }
     fn push_settings_data(
-        paths: &mut Vec<String>,
+        mut search_paths: SearchPaths,
         mut settings_data: SettingsData,
         main_window: &MainWindow,
     ) {
-        let new_search_paths: Rc<VecModel<SharedString>> = Rc::new(VecModel::from(
-            paths
-                .iter()
-                .map(|x| SharedString::from(x))
-                .collect::<Vec<_>>(),
-        ));
-        // Convert it to a ModelRc.
-        let new_new_search_paths = ModelRc::from(new_search_paths.clone());
-        settings_data.search_paths = new_new_search_paths;
+        let strings = |x: &Vec<String>| {
+            ModelRc::new(VecModel::from(
+                x.iter().map(|x| SharedString::from(x)).collect::<Vec<_>>(),
+            ))
+        };
+        let len = search_paths.paths.len();
+        search_paths.recursive.resize(len, false);
+        search_paths
+            .include
+            .resize(len, DEFAULT_INCLUDE_GLOBS.to_string());
+        search_paths.exclude.resize(len, String::new());
+
+        settings_data.search_paths = strings(&search_paths.paths);
+        settings_data.recursive_paths = ModelRc::new(VecModel::from(search_paths.recursive));
+        settings_data.include_globs = strings(&search_paths.include);
+        settings_data.exclude_globs = strings(&search_paths.exclude);
         main_window.set_settings_data(settings_data.clone());
         save_config(settings_data.clone());
     }
@@ -270,15 +708,11 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
         let main_window_weak = main_window.as_weak();
         main_window.on_settings_add_path(move || {
             if let Some(main_window) = main_window_weak.upgrade() {
-                let settings_data = load_config().unwrap();
-                let paths: &mut Vec<String> = &mut settings_data
-                    .search_paths
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect();
-                paths.push(String::new());
+                let settings_data = load_config();
+                let mut search_paths = SearchPaths::of(&settings_data);
+                search_paths.paths.push(String::new());
 
-                push_settings_data(paths, settings_data, &main_window);
+                push_settings_data(search_paths, settings_data, &main_window);
             }
         });
     }
@@ -286,19 +720,13 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
         let main_window_weak = main_window.as_weak();
         main_window.on_settings_remove_path(move |i| {
             if let Some(main_window) = main_window_weak.upgrade() {
-                let settings_data = load_config().unwrap();
-                let paths: &mut Vec<String> = &mut settings_data
-                    .search_paths
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect();
-                if i >= 0 && i < paths.len() as i32 {
-                    paths.remove(i as usize);
-                } else if i == 0 && paths.len() == 1 {
-                    *paths = vec![];
+                let settings_data = load_config();
+                let mut search_paths = SearchPaths::of(&settings_data);
+                if i >= 0 && i < search_paths.paths.len() as i32 {
+                    search_paths.remove(i as usize);
                 }
 
-                push_settings_data(paths, settings_data, &main_window);
+                push_settings_data(search_paths, settings_data, &main_window);
             }
         });
     }
@@ -306,28 +734,347 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
         let main_window_weak = main_window.as_weak();
         main_window.on_settings_edit_path(move |i, s| {
             if let Some(main_window) = main_window_weak.upgrade() {
-                let settings_data = load_config().unwrap();
-                let paths: &mut Vec<String> = &mut settings_data
-                    .search_paths
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect();
-                if i >= 0 && i < paths.len() as i32 {
-                    paths[i as usize] = s.to_string();
+                let settings_data = load_config();
+                let mut search_paths = SearchPaths::of(&settings_data);
+                if i >= 0 && i < search_paths.paths.len() as i32 {
+                    search_paths.paths[i as usize] = s.to_string();
+                }
+
+                push_settings_data(search_paths, settings_data, &main_window);
+            }
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_settings_set_include(move |i, s| {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let settings_data = load_config();
+                let mut search_paths = SearchPaths::of(&settings_data);
+                if i >= 0 && (i as usize) < search_paths.include.len() {
+                    search_paths.include[i as usize] = s.to_string();
+                }
+
+                push_settings_data(search_paths, settings_data, &main_window);
+            }
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_settings_set_exclude(move |i, s| {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let settings_data = load_config();
+                let mut search_paths = SearchPaths::of(&settings_data);
+                if i >= 0 && (i as usize) < search_paths.exclude.len() {
+                    search_paths.exclude[i as usize] = s.to_string();
+                }
+
+                push_settings_data(search_paths, settings_data, &main_window);
+            }
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_settings_set_recursive(move |i, checked| {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let settings_data = load_config();
+                let mut search_paths = SearchPaths::of(&settings_data);
+                search_paths.recursive.resize(search_paths.paths.len(), false);
+                if i >= 0 && i < search_paths.paths.len() as i32 {
+                    search_paths.recursive[i as usize] = checked;
                 }
 
-                push_settings_data(paths, settings_data, &main_window);
+                push_settings_data(search_paths, settings_data, &main_window);
             }
         });
     }
 
+    // Automatic processing
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_clear_processing_queue(move || {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                PROCESSING_QUEUE.lock().unwrap().clear_finished();
+                set_processing_queue_data(&main_window);
+            }
+        });
+    }
+
+    // Validation
+    {
+        let main_window_weak = main_window.as_weak();
+        let cur_path_clone = cur_path.clone();
+        main_window.on_acknowledge_flags(move || {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let path = cur_path_clone.lock().unwrap().clone();
+                if let Some(path) = path.as_ref() {
+                    let path = Path::new(path);
+                    if let Err(e) = acknowledge_flags(path) {
+                        show_error("Error acknowledging flags", e);
+                    }
+                    main_window.set_unacknowledged_flags(unacknowledged_flags(path) as i32);
+                    refresh_lif_file_entry(&main_window, path);
+                }
+            }
+        });
+    }
+
+    // Next round
+    {
+        let cur_path_clone = cur_path.clone();
+        main_window.on_write_next_round_clicked(move || {
+            let path = cur_path_clone.lock().unwrap().clone();
+            if let Some(path) = path {
+                write_next_round_confirmed(Path::new(&path));
+            }
+        });
+    }
+
+    // Mass start sprints
+    {
+        let main_window_weak = main_window.as_weak();
+        let cur_path_clone = cur_path.clone();
+        main_window.on_save_sprints(move |raw| {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let path = cur_path_clone.lock().unwrap().clone();
+                if let Some(path) = path {
+                    if let Err(e) = parse_sprints(&raw) {
+                        let _ = native_dialog::DialogBuilder::message()
+                            .set_level(MessageLevel::Error)
+                            .set_title("Error reading sprints")
+                            .set_text(e)
+                            .alert()
+                            .show();
+                        return;
+                    }
+
+                    let sprints = sprints_path(Path::new(&path));
+                    if let Err(e) = std::fs::write(&sprints, raw.to_string()) {
+                        show_error(
+                            "Error saving sprints",
+                            format!("Error writing to {}, {}", sprints.display(), e),
+                        );
+                        return;
+                    }
+                    RENDER_CACHE.lock().unwrap().invalidate(Path::new(&path));
+                    main_window.invoke_table_changed();
+                }
+            }
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_import_sprints(move || {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let path = native_dialog::DialogBuilder::file()
+                    .set_title("Import Sprint Placings")
+                    .add_filter("Sprint Placings", ["csv", "txt", "sprints"])
+                    .open_single_file()
+                    .show()
+                    .unwrap();
+                if let Some(path) = path {
+                    match std::fs::read_to_string(&path) {
+                        Ok(raw) => main_window.set_sprint_entry(raw.into()),
+                        Err(e) => show_error(
+                            "Error importing sprints",
+                            format!("Failed to read {}, {}", path.display(), e),
+                        ),
+                    }
+                }
+            }
+        });
+    }
+
+    // Records
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_settings_browse_records(move || {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let path = native_dialog::DialogBuilder::file()
+                    .set_title("Select Records List")
+                    .add_filter("CSV", ["csv"])
+                    .open_single_file()
+                    .show()
+                    .unwrap();
+                if let Some(path) = path {
+                    let mut settings_data = main_window.get_settings_data();
+                    settings_data.records_path = path.to_string_lossy().to_string().into();
+                    main_window.set_settings_data(settings_data.clone());
+                    save_config(settings_data);
+                }
+            }
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_settings_browse_registrations(move || {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let path = native_dialog::DialogBuilder::file()
+                    .set_title("Select People File")
+                    .add_filter("People Files", ["csv", "ppl"])
+                    .open_single_file()
+                    .show()
+                    .unwrap();
+                if let Some(path) = path {
+                    let mut settings_data = main_window.get_settings_data();
+                    settings_data.registration_path = path.to_string_lossy().to_string().into();
+                    main_window.set_settings_data(settings_data.clone());
+                    save_config(settings_data);
+                }
+            }
+        });
+    }
+    main_window.on_records_report_clicked(move || {
+        let broken_records = match meet_records(&load_config(), None) {
+            Ok(x) => x.1,
+            Err(e) => {
+                let _ = native_dialog::DialogBuilder::message()
+                    .set_level(MessageLevel::Error)
+                    .set_title("Error reading records")
+                    .set_text(e)
+                    .alert()
+                    .show();
+                return;
+            }
+        };
+
+        let mut doc = gen_records_report_pdf(&broken_records).expect("PDF generation failed");
+        let path = native_dialog::DialogBuilder::file()
+            .set_title("Save Records Report")
+            .set_filename("records.pdf")
+            .add_filter("PDF Document", ["pdf"])
+            .save_single_file()
+            .show()
+            .unwrap();
+        if let Some(path) = path {
+            if let Err(e) = std::fs::write(&path, doc.to_bytes().expect("PDF generation failed")) {
+                show_error(
+                    "Error saving records report",
+                    format!("Error writing {}, {}", path.display(), e),
+                );
+            }
+        }
+    });
+
+    // Club standings
+    main_window.on_club_standings_clicked(move || {
+        let settings = load_config();
+        let points_per_place = match parse_points(&settings.club_points) {
+            Ok(x) => x,
+            Err(e) => {
+                let _ = native_dialog::DialogBuilder::message()
+                    .set_level(MessageLevel::Error)
+                    .set_title("Error reading club points")
+                    .set_text(e)
+                    .alert()
+                    .show();
+                return;
+            }
+        };
+
+        let standings = club_standings(
+            &load_meet(&load_config()),
+            &points_per_place,
+            settings.team_counting_skater.max(1) as usize,
+        );
+
+        let mut doc = gen_club_standings_pdf(&standings).expect("PDF generation failed");
+        let path = native_dialog::DialogBuilder::file()
+            .set_title("Save Club Standings")
+            .set_filename("standings.pdf")
+            .add_filter("PDF Document", ["pdf"])
+            .save_single_file()
+            .show()
+            .unwrap();
+        // The CSV goes alongside the PDF for anyone keeping a spreadsheet
+        if let Some(path) = path {
+            let csv = path.with_extension("csv");
+            let written = std::fs::write(&path, doc.to_bytes().expect("PDF generation failed"))
+                .map_err(|e| format!("Error writing {}, {}", path.display(), e))
+                .and_then(|_| {
+                    std::fs::write(&csv, standings_csv(&standings))
+                        .map_err(|e| format!("Error writing {}, {}", csv.display(), e))
+                });
+            if let Err(e) = written {
+                show_error("Error saving club standings", e);
+            }
+        }
+    });
+
+    // Skater lookup
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_skater_search(move |query| {
+            if let Some(main_window) = main_window_weak.upgrade() {
+                let row_data: Rc<VecModel<ModelRc<StandardListViewItem>>> =
+                    Rc::new(VecModel::default());
+                for appearance in find_skater(&load_meet(&load_config()), &query) {
+                    let items = Rc::new(VecModel::default());
+                    for entry in gen_appearance_row(&appearance) {
+                        items.push(SharedString::from(entry).into());
+                    }
+                    row_data.push(items.into());
+                }
+
+                main_window.set_skater_results(row_data.into());
+            }
+        });
+    }
+    main_window.on_skater_report(move |query| {
+        let appearances = find_skater(&load_meet(&load_config()), &query);
+        if appearances.is_empty() {
+            return;
+        }
+
+        let mut doc = gen_skater_report_pdf(&query, &appearances).expect("PDF generation failed");
+        let path = native_dialog::DialogBuilder::file()
+            .set_title("Save Skater Report")
+            .set_filename(format!("{}.pdf", query.trim()))
+            .add_filter("PDF Document", ["pdf"])
+            .save_single_file()
+            .show()
+            .unwrap();
+        if let Some(path) = path {
+            if let Err(e) = std::fs::write(&path, doc.to_bytes().expect("PDF generation failed")) {
+                show_error(
+                    "Error saving skater report",
+                    format!("Error writing {}, {}", path.display(), e),
+                );
+            }
+        }
+    });
+
     // Printing
     {
-        let pub_pdf_clone = pub_pdf_bitmap.clone();
+        let main_window_weak = main_window.as_weak();
+        let cur_path_clone = cur_path.clone();
+        let cur_render_clone = cur_render.clone();
         main_window.on_print_button_clicked(move || {
-            let pdf = pub_pdf_clone.borrow();
-            if let Some(doc) = pdf.as_ref() {
-                crate::print::print_document(&doc.0, doc.1, doc.2);
+            // Taken out so the worker isn't held up while the print dialog is open
+            let render = cur_render_clone.lock().unwrap().clone();
+            if let Some(render) = render {
+                // The preview may be drawn too coarsely for paper, so the pages are drawn again
+                let (images, width, height) =
+                    match render_pdf(render.pdf.clone(), PRINT_SCALE, |_, _| true) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            show_error(
+                                "Error printing",
+                                format!("Failed to render PDF for printing, {}", e),
+                            );
+                            return;
+                        }
+                    };
+                crate::print::print_document(&images, width, height);
+
+                if let Some(path) = cur_path_clone.lock().unwrap().clone().as_ref() {
+                    let path = Path::new(path);
+                    if let Err(e) = mark_printed(path) {
+                        report_error(e);
+                    }
+                    if let Some(main_window) = main_window_weak.upgrade() {
+                        refresh_lif_file_entry(&main_window, path);
+                    }
+                }
             }
         });
     }
@@ -335,11 +1082,23 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
     // Saving
     {
         let main_window_weak = main_window.as_weak();
-        let pub_pdf_clone = pub_pdf_document.clone();
+        let cur_path_clone = cur_path.clone();
+        let cur_render_clone = cur_render.clone();
         main_window.on_save_button_clicked(move || {
             if let Some(main_window) = main_window_weak.upgrade() {
-                let pdf = pub_pdf_clone.borrow();
-                if let Some(doc) = pdf.as_ref() {
+                let render = cur_render_clone.lock().unwrap().clone();
+                if let Some(render) = render {
+                    // The copy in the output directory is only written when a race is saved or
+                    // processed, never just for looking at it
+                    let lif_path = cur_path_clone.lock().unwrap().clone();
+                    if let Some(lif_path) = lif_path.filter(|_| load_config().pdf_output_enabled) {
+                        let lif_path = Path::new(&lif_path);
+                        if let Err(e) = save_output_pdf(&render.pdf, &render.event, lif_path) {
+                            show_error("Error saving PDF", e);
+                        }
+                        refresh_lif_file_entry(&main_window, lif_path);
+                    }
+
                     let path = native_dialog::DialogBuilder::file()
                         .set_title("Save PDF")
                         .set_filename(format!("{}.pdf", main_window.get_event().event_code))
@@ -348,7 +1107,21 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
                         .show()
                         .unwrap();
                     if let Some(path) = path {
-                        let _ = std::fs::write(path, doc);
+                        if let Err(e) = std::fs::write(&path, render.pdf.as_slice()) {
+                            show_error(
+                                "Error saving PDF",
+                                format!("Error writing {}, {}", path.display(), e),
+                            );
+                            return;
+                        }
+
+                        if let Some(lif_path) = cur_path_clone.lock().unwrap().clone().as_ref() {
+                            let lif_path = Path::new(lif_path);
+                            if let Err(e) = mark_pdf_generated(lif_path) {
+                                report_error(e);
+                            }
+                            refresh_lif_file_entry(&main_window, lif_path);
+                        }
                     }
                 }
             }
@@ -358,75 +1131,711 @@ pub fn interface_main_window(main_window: &MainWindow) -> Result<(), slint::Plat
     Ok(())
 }
 
-fn generate_display_pdf(
-    cur_path_clone: &Rc<RefCell<Option<String>>>,
-    pub_pdf_img_clone: &Rc<RefCell<Option<(Vec<Vec<u8>>, u32, u32)>>>,
-    pub_pdf_doc_clone: &Rc<RefCell<Option<Vec<u8>>>>,
-    main_window: MainWindow,
+fn progression_settings(settings: &SettingsData) -> Result<(AdvancementRule, Seeding), String> {
+    let rule = AdvancementRule {
+        top_per_heat: settings.advance_top_per_heat.clamp(0, 255) as u8,
+        best_time_qualifiers: settings.advance_best_times.clamp(0, 255) as u8,
+    };
+    let seeding = Seeding::from_index(settings.seeding_method, &settings.draw_table.to_string())?;
+
+    Ok((rule, seeding))
+}
+
+// The race seeded into its next round, as long as every heat of its round that the operator
+// scheduled in lynx.evt has a result
+fn next_round_of(
+    path: &Path,
+    settings: &SettingsData,
+    evt_path: &Path,
+) -> Result<RaceEvent, String> {
+    let (rule, seeding) = progression_settings(settings)?;
+    let mut event = load_race(path)?;
+    let heats_found = progress_event(
+        &mut event,
+        path,
+        rule,
+        &seeding,
+        settings.next_round_heat_size.max(0) as usize,
+    );
+
+    let round = event
+        .event
+        .round
+        .map(|x| x.to_string())
+        .unwrap_or_default();
+    match scheduled_heats(evt_path, &event.event.event_code, event.event.round) {
+        None => Err(format!(
+            "Round {} of event {} isn't scheduled in {}, so there's no telling whether every \
+             heat has finished",
+            round,
+            event.event.event_code,
+            evt_path.display()
+        )),
+        Some(scheduled) if heats_found < scheduled => Err(format!(
+            "Only {} of the {} heats of round {} of event {} have results",
+            heats_found, scheduled, round, event.event.event_code
+        )),
+        Some(_) if event.next_round.is_empty() => {
+            Err("No skaters qualified for the next round".to_string())
+        }
+        Some(_) => Ok(event),
+    }
+}
+
+// Writing lynx.evt changes what FinishLynx runs next, so it's only done when asked for and
+// confirmed
+fn write_next_round_confirmed(path: &Path) {
+    let settings = load_config();
+    let evt_path = Path::new(&settings.evt_output_path.to_string()).join("lynx.evt");
+    let event = match next_round_of(path, &settings, &evt_path) {
+        Ok(x) => x,
+        Err(e) => {
+            show_error("Next round not written", e);
+            return;
+        }
+    };
+
+    let confirmed = native_dialog::DialogBuilder::message()
+        .set_level(MessageLevel::Warning)
+        .set_title("Write Next Round")
+        .set_text(format!(
+            "Write {} heats of round {} of event {} to {}? Heats of that round already in the \
+             file are replaced. The file is copied to {} first.",
+            event.next_round.len(),
+            event.event.round.unwrap_or(1).saturating_add(1),
+            event.event.event_code,
+            evt_path.display(),
+            backup_path(&evt_path).display()
+        ))
+        .confirm()
+        .show()
+        .unwrap_or(false);
+    if !confirmed {
+        return;
+    }
+
+    if let Err(e) = write_next_round(&evt_path, &event.event, &event.next_round) {
+        show_error("Error writing next round", e);
+    }
+}
+
+// The imported records as they stood before the race at `until` was skated, or after the whole
+// meet, along with every record broken on the way. The meet comes in file order, which FinishLynx
+// numbers by event, round and heat. Timesheets and the records report both go through here, so a
+// race marked as breaking a record is the same one the report lists.
+fn meet_records(
+    settings: &SettingsData,
+    until: Option<&Path>,
+) -> Result<(Vec<Record>, Vec<BrokenRecord>), String> {
+    let mut records = load_records(&settings.records_path)?;
+    if records.is_empty() {
+        return Ok((records, vec![]));
+    }
+
+    let registrations = load_registrations(&settings.registration_path).unwrap_or_default();
+    let rules = parse_category_rules(&settings.category_rules).unwrap_or_default();
+    let mut broken_records = vec![];
+    for (path, mut event) in load_meet(settings) {
+        if until.is_some_and(|x| path.as_path() >= x) {
+            break;
+        }
+        apply_categories(&mut event, &registrations, &rules, race_date(&path));
+        mark_records(&mut event, &mut records);
+        broken_records.extend(event.broken_records);
+    }
+
+    Ok((records, broken_records))
+}
+
+// Everything worked out from a parsed race before it is shown or printed
+fn prepare_event(mut event: RaceEvent, path: &Path) -> RaceEvent {
+    let settings = load_config();
+
+    // Checked while the skaters are still in file order, and before auto-print looks at the flags
+    let track = TrackType::from_index(settings.track_type);
+    event.event.infer_laps(track);
+    if let Err(e) = flag_lap_count_mismatches(path, &event) {
+        report_error(e);
+    }
+
+    if settings.progression_enabled {
+        match progression_settings(&settings) {
+            Ok((rule, seeding)) => {
+                progress_event(
+                    &mut event,
+                    path,
+                    rule,
+                    &seeding,
+                    settings.next_round_heat_size.max(0) as usize,
+                );
+            }
+            Err(e) => report_error(format!("Next round not seeded, {}", e)),
+        }
+    }
+
+    apply_metrics(&mut event, track);
+    if let Err(e) = record_and_mark(&mut event, path) {
+        report_error(e);
+    }
+
+    match (
+        load_registrations(&settings.registration_path),
+        parse_category_rules(&settings.category_rules),
+    ) {
+        (Ok(registrations), Ok(rules)) => {
+            apply_categories(&mut event, &registrations, &rules, race_date(path))
+        }
+        (Err(e), _) | (_, Err(e)) => report_error(e),
+    }
+
+    match meet_records(&settings, Some(path)) {
+        Ok((mut records, _)) => mark_records(&mut event, &mut records),
+        Err(e) => report_error(e),
+    }
+
+    if is_team_event(&event) {
+        event.teams = group_teams(&event, settings.team_counting_skater.max(1) as usize);
+    }
+
+    if settings.mass_start_enabled && is_mass_start(&event, path) {
+        let table = PointsTable {
+            sprint: parse_points(&settings.sprint_points).unwrap_or_else(|e| {
+                report_error(e);
+                vec![]
+            }),
+            finish: parse_points(&settings.finish_points).unwrap_or_else(|e| {
+                report_error(e);
+                vec![]
+            }),
+        };
+
+        let raw = std::fs::read_to_string(sprints_path(path)).unwrap_or_default();
+        match parse_sprints(&raw) {
+            Ok(sprints) => event.mass_start = Some(score_mass_start(&event, &sprints, &table)),
+            Err(e) => report_error(format!("Failed to read sprints for {}, {}", path.display(), e)),
+        }
+    }
+
+    event.revision = latest_revision(path);
+
+    event
+}
+
+fn set_mass_start_data(main_window: &MainWindow, event: &RaceEvent, path: &Path) {
+    let row_data: Rc<VecModel<ModelRc<StandardListViewItem>>> = Rc::new(VecModel::default());
+    if let Some(scoring) = &event.mass_start {
+        for row in gen_points_preview_rows(scoring) {
+            let items = Rc::new(VecModel::default());
+            for entry in row {
+                items.push(SharedString::from(entry).into());
+            }
+            row_data.push(items.into());
+        }
+    }
+
+    main_window.set_mass_start_present(event.mass_start.is_some());
+    main_window.set_points_table_data(row_data.into());
+    main_window.set_sprint_entry(
+        std::fs::read_to_string(sprints_path(path))
+            .unwrap_or_default()
+            .into(),
+    );
+}
+
+fn set_revision_data(main_window: &MainWindow, event: &RaceEvent) {
+    match &event.revision {
+        Some(revision) => {
+            main_window.set_revision_marker(revision.marker().into());
+            main_window.set_revision_changes(revision.changes.join("\n").into());
+        }
+        None => {
+            main_window.set_revision_marker(SharedString::new());
+            main_window.set_revision_changes(SharedString::new());
+        }
+    }
+}
+
+fn set_team_data(main_window: &MainWindow, event: &RaceEvent) {
+    let row_data: Rc<VecModel<ModelRc<StandardListViewItem>>> = Rc::new(VecModel::default());
+    for team in &event.teams {
+        let items = Rc::new(VecModel::default());
+        for entry in gen_team_row(team) {
+            items.push(SharedString::from(entry).into());
+        }
+        row_data.push(items.into());
+    }
+
+    main_window.set_team_event(event.teams.len() > 0);
+    main_window.set_team_table_data(row_data.into());
+}
+
+fn metric_columns(settings: &SettingsData) -> MetricColumns {
+    MetricColumns {
+        gap_to_leader: settings.pdf_gap_to_leader,
+        gap_to_previous: settings.pdf_gap_to_previous,
+        speed: settings.pdf_speed,
+        lap_average: settings.pdf_lap_average,
+    }
+}
+
+// Writes the race's PDF to the output directory under the configured name and policy, and notes
+// that it has been generated
+fn save_output_pdf(pdf: &[u8], event: &RaceEvent, path: &Path) -> Result<PathBuf, String> {
+    let settings = load_config();
+    let dir = settings.pdf_output_path.to_string();
+    if dir.trim().is_empty() {
+        return Err("No PDF output directory set".to_string());
+    }
+    if !std::fs::exists(&dir).unwrap_or(false) {
+        let _ = std::fs::create_dir_all(&dir);
+    }
+
+    let revision = event.revision.as_ref().map(|x| x.number).unwrap_or(1);
+    // A corrected race that was already put out never silently replaces the PDF it had before,
+    // so under the overwrite policy its revisions are written beside it
+    let policy = match OutputPolicy::from_index(settings.output_policy)? {
+        OutputPolicy::Overwrite if revision > 1 => OutputPolicy::Revisions,
+        x => x,
+    };
+    let written = race_date(path).unwrap_or_else(now_secs);
+    let name = render_file_name(&settings.output_name_template, event, written);
+    let out = output_path(Path::new(&dir), &name, revision, policy);
+
+    // Writing the same revision again replaces it, anything else that would be lost is archived
+    if policy == OutputPolicy::Archive && std::fs::exists(&out).unwrap_or(false) {
+        let race = path.to_string_lossy().to_string();
+        match output_file_owner(path, &out) {
+            Some((owner, x)) if owner == race && x == revision => {}
+            Some((owner, x)) if owner == race => archive_existing(&out, Some(x))?,
+            _ => archive_existing(&out, None)?,
+        }
+    }
+
+    std::fs::write(&out, pdf).map_err(|e| format!("Error writing {}, {}", out.display(), e))?;
+    record_output_file(path, &out, revision)?;
+    mark_pdf_generated(path)?;
+
+    Ok(out)
+}
+
+// Parses, validates and writes the PDF of a race without it being opened, then prints it if
+// auto-print is on
+fn process_race(path: &Path) -> Result<String, String> {
+    let event = prepare_event(load_race(path)?, path);
+    let pdf = gen_timesheet_pdf(event.clone(), metric_columns(&load_config()))
+        .map_err(|e| e.to_string())
+        .and_then(|mut x| x.to_bytes().map_err(|e| e.to_string()))
+        .map_err(|e| format!("PDF generation failed, {}", e))?;
+    let out = save_output_pdf(&pdf, &event, path)?;
+
+    let settings = load_config();
+    let flags = unacknowledged_flags(path);
+    let mut notes = vec![];
+    if let Some(revision) = &event.revision {
+        notes.push(format!(
+            "Revision {}, {} changes",
+            revision.number,
+            revision.changes.len()
+        ));
+    }
+    if flags > 0 {
+        notes.push(format!("{} unacknowledged flags", flags));
+    }
+
+    if settings.auto_print_enabled {
+        // Races that need checking, with time discrepancies or lap counts that don't match the
+        // distance, stay off the printer until someone has looked at them
+        if flags > 0 {
+            notes.push("Not printed".to_string());
+        } else {
+            let copies = settings.auto_print_copies.max(1) as u32;
+            print_pdf_file(&out, &settings.auto_print_printer, copies)?;
+            mark_printed(path)?;
+            notes.push(match copies {
+                1 => "Printed".to_string(),
+                _ => format!("Printed {} copies", copies),
+            });
+        }
+    }
+
+    Ok(notes.join("; "))
+}
+
+fn queue_auto_process(main_window: &MainWindow, paths: Vec<PathBuf>) {
+    let start = {
+        let mut queue = PROCESSING_QUEUE.lock().unwrap();
+        for path in paths {
+            queue.push(path);
+        }
+        queue.start()
+    };
+    set_processing_queue_data(main_window);
+    if !start {
+        return;
+    }
+
+    let main_window_weak = main_window.as_weak();
+    std::thread::spawn(move || {
+        loop {
+            let next = PROCESSING_QUEUE.lock().unwrap().next();
+            let path = match next {
+                Some(x) => x,
+                None => break,
+            };
+            let _ = main_window_weak.upgrade_in_event_loop(|main_window| {
+                set_processing_queue_data(&main_window);
+            });
+
+            let result = process_race(&path);
+            if let Err(e) = &result {
+                report_error(format!("Failed to process {}, {}", path.display(), e));
+            }
+            PROCESSING_QUEUE.lock().unwrap().finish(&path, result);
+
+            let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+                set_processing_queue_data(&main_window);
+                refresh_lif_file_entry(&main_window, &path);
+            });
+        }
+    });
+}
+
+fn set_processing_queue_data(main_window: &MainWindow) {
+    let row_data: Rc<VecModel<ModelRc<StandardListViewItem>>> = Rc::new(VecModel::default());
+    for item in PROCESSING_QUEUE.lock().unwrap().items() {
+        let items = Rc::new(VecModel::default());
+        for entry in gen_queue_row(item) {
+            items.push(SharedString::from(entry).into());
+        }
+        row_data.push(items.into());
+    }
+
+    main_window.set_processing_queue_data(row_data.into());
+}
+
+// What the Print and Save buttons act on, filled in by the preview worker
+#[derive(Clone)]
+struct Preview {
+    cur_path: Arc<Mutex<Option<String>>>,
+    cur_render: Arc<Mutex<Option<Arc<RenderedRace>>>>,
+}
+
+// Bumped each time a race is selected or the zoom changes, so a worker can tell when its work is
+// no longer wanted
+static PREVIEW_JOB: AtomicU64 = AtomicU64::new(0);
+// Width in physical pixels the viewer shows a page at
+static PREVIEW_WIDTH: AtomicU32 = AtomicU32::new(0);
+
+// Timesheets are A4, 595 points across
+const PAGE_WIDTH_PT: f32 = 595.0;
+// Pages are drawn this coarsely first so there's something to look at while the sharp render runs
+const DRAFT_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 8.0;
+// Used until the viewer has been laid out
+const DEFAULT_SCALE: f32 = 2.0;
+
+fn is_current(job: u64) -> bool {
+    PREVIEW_JOB.load(Ordering::SeqCst) == job
+}
+
+// Pixels per point needed to show a page sharply at the viewer's zoom level
+fn preview_scale() -> f32 {
+    let width = PREVIEW_WIDTH.load(Ordering::SeqCst);
+    if width == 0 {
+        return DEFAULT_SCALE;
+    }
+    let resolution = load_config().preview_resolution.max(25) as f32 / 100.0;
+
+    (width as f32 * resolution / PAGE_WIDTH_PT).clamp(DRAFT_SCALE, MAX_SCALE)
+}
+
+fn show_race(main_window: &MainWindow, event: RaceEvent, path: &Path) {
+    set_mass_start_data(main_window, &event, path);
+    set_team_data(main_window, &event);
+    set_revision_data(main_window, &event);
+    main_window.set_unacknowledged_flags(unacknowledged_flags(path) as i32);
+    let event: SlintRaceEvent = event.into();
+    main_window.set_event(event.event);
+    main_window.set_table_data(gen_sorted_table(main_window, &event.competitors).into());
+    main_window.set_race_event_set(true);
+}
+
+// `first` is set when the pages belong to a newly selected race rather than a re-render
+fn show_pages(main_window: &MainWindow, render: Arc<RenderedRace>, preview: &Preview, first: bool) {
+    let mut slint_imgs = vec![];
+    for image in &render.images {
+        slint_imgs.push(slint::Image::from_rgba8(
+            slint::SharedPixelBuffer::clone_from_slice(image, render.width, render.height),
+        ));
+    }
+    main_window.set_pdf_images(ModelRc::new(VecModel::from(slint_imgs)));
+    if first {
+        main_window.set_pdf_page(0);
+        main_window.set_tab_index(1);
+    }
+
+    *preview.cur_render.lock().unwrap() = Some(render);
+}
+
+fn set_preview_progress(main_window: &MainWindow, busy: bool, progress: f32, status: &str) {
+    main_window.set_preview_busy(busy);
+    main_window.set_preview_progress(progress);
+    main_window.set_preview_status(SharedString::from(status));
+}
+
+fn post_preview_progress(
+    main_window_weak: &Weak<MainWindow>,
+    job: u64,
+    busy: bool,
+    progress: f32,
+    status: &'static str,
 ) {
-    let path = cur_path_clone.borrow();
-    if let Some(path) = path.as_ref() {
-        let mut f = std::fs::File::open(path).expect("Failed to open file");
-        let mut buffer = Vec::new();
-        f.read_to_end(&mut buffer)
-            .expect("Failed to read bytes of file");
-
-        let mut file_contents = String::new();
-        for byte in buffer {
-            file_contents.push(byte as char);
-        }
-
-        match RaceEvent::parse_lif(file_contents, path.clone()) {
-            Ok(event) => {
-                let mut pdf = gen_timesheet_pdf(event.clone()).expect("PDF generation failed");
-
-                let mut pub_pdf_doc = pub_pdf_doc_clone.borrow_mut();
-                *pub_pdf_doc = Some(pdf.to_bytes().unwrap());
-
-                let (images, width, height) = pdf_to_image(&mut pdf).unwrap();
-                let mut slint_imgs = vec![];
-                for image in &images {
-                    let img_buf = slint::Image::from_rgba8(
-                        slint::SharedPixelBuffer::clone_from_slice(&image, width, height),
-                    );
-                    slint_imgs.push(img_buf);
-                }
+    let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+        if is_current(job) {
+            set_preview_progress(&main_window, busy, progress, status);
+        }
+    });
+}
 
-                main_window.set_pdf_images(ModelRc::new(VecModel::from(slint_imgs)));
-                main_window.set_tab_index(1);
+// Parses, renders and shows the race off the UI thread. Selecting another race before this one
+// is done abandons it at the next step.
+fn start_preview_job(main_window: &MainWindow, path: PathBuf, preview: Preview) {
+    let job = PREVIEW_JOB.fetch_add(1, Ordering::SeqCst) + 1;
+    set_preview_progress(main_window, true, 0.0, "Reading race");
 
-                if load_config().unwrap().pdf_output_enabled {
-                    if !std::fs::exists(load_config().unwrap().pdf_output_path).unwrap() {
-                        let _ = std::fs::create_dir_all(load_config().unwrap().pdf_output_path);
-                    }
+    let main_window_weak = main_window.as_weak();
+    std::thread::spawn(move || {
+        if let Some(render) = render_race(&path, job, &preview, &main_window_weak) {
+            sharpen_pages(render, job, &preview, &main_window_weak);
+        }
+    });
+}
+
+fn show_parse_error(main_window_weak: &Weak<MainWindow>, job: u64, message: String) {
+    let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+        if !is_current(job) {
+            return;
+        }
+        set_preview_progress(&main_window, false, 0.0, "");
+        let _ = native_dialog::DialogBuilder::message()
+            .set_level(MessageLevel::Error)
+            .set_title("Error parsing file")
+            .set_text(message)
+            .alert()
+            .show();
+    });
+}
 
-                    pdf.save(format!(
-                        "{}/{}.pdf",
-                        load_config().unwrap().pdf_output_path,
-                        event.event.event_code
-                    ))
-                        .expect("Error writing PDF to disk");
+// Shows the race and its pages. Races seen before with the same file and settings come straight
+// from the render cache, others are parsed, prepared and drawn in draft.
+fn render_race(
+    path: &Path,
+    job: u64,
+    preview: &Preview,
+    main_window_weak: &Weak<MainWindow>,
+) -> Option<Arc<RenderedRace>> {
+    let key = match RenderKey::new(path, config_hash()) {
+        Some(x) => x,
+        None => {
+            show_parse_error(main_window_weak, job, format!("Failed to read {}", path.display()));
+            return None;
+        }
+    };
+    let cached = RENDER_CACHE.lock().unwrap().get(&key);
+    let (render, shown) = match cached {
+        Some(render) => (render, false),
+        None => (draw_race(path, key, job, preview, main_window_weak)?, true),
+    };
+
+    {
+        let render = render.clone();
+        let path = path.to_path_buf();
+        let preview = preview.clone();
+        let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+            if is_current(job) {
+                if !shown {
+                    show_race(&main_window, render.event.clone(), &path);
+                    *preview.cur_path.lock().unwrap() = Some(path.to_string_lossy().to_string());
                 }
+                show_pages(&main_window, render, &preview, true);
+            }
+        });
+    }
+
+    Some(render)
+}
 
-                let mut pub_pdf_img = pub_pdf_img_clone.borrow_mut();
-                *pub_pdf_img = Some((images, width, height));
+// Parses the race and generates its PDF, showing the race as soon as it's read, then draws a
+// draft of the pages and caches them
+fn draw_race(
+    path: &Path,
+    key: RenderKey,
+    job: u64,
+    preview: &Preview,
+    main_window_weak: &Weak<MainWindow>,
+) -> Option<Arc<RenderedRace>> {
+    let event = match load_race(path) {
+        Ok(event) => prepare_event(event, path),
+        Err(e) => {
+            show_parse_error(
+                main_window_weak,
+                job,
+                format!("Failed to parse {}, {}", path.display(), e),
+            );
+            return None;
+        }
+    };
+    if !is_current(job) {
+        return None;
+    }
+
+    {
+        let event = event.clone();
+        let path = path.to_path_buf();
+        let preview = preview.clone();
+        let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+            if is_current(job) {
+                show_race(&main_window, event, &path);
+                *preview.cur_path.lock().unwrap() = Some(path.to_string_lossy().to_string());
+                // The last race's pages mustn't be printed or saved under this one
+                *preview.cur_render.lock().unwrap() = None;
             }
+        });
+    }
+    post_preview_progress(main_window_weak, job, true, 0.1, "Generating PDF");
+
+    let pdf = gen_timesheet_pdf(event.clone(), metric_columns(&load_config()))
+        .map_err(|e| e.to_string())
+        .and_then(|mut x| x.to_bytes().map_err(|e| e.to_string()));
+    let pdf = match pdf {
+        Ok(x) => Arc::new(x),
+        Err(e) => {
+            report_error(format!("Failed to generate PDF for {}, {}", path.display(), e));
+            post_preview_progress(main_window_weak, job, false, 0.0, "");
+            return None;
+        }
+    };
+    if !is_current(job) {
+        return None;
+    }
+
+    post_preview_progress(main_window_weak, job, true, 0.2, "Drawing pages");
+    let draft = render_pdf(pdf.clone(), DRAFT_SCALE, |_, _| is_current(job));
+    let (images, width, height) = match draft {
+        Ok(x) => x,
+        Err(e) => {
+            if is_current(job) {
+                report_error(format!("Failed to render {}, {}", path.display(), e));
+                post_preview_progress(main_window_weak, job, false, 0.0, "");
+            }
+            return None;
+        }
+    };
+
+    let render = Arc::new(RenderedRace {
+        key,
+        event,
+        pdf,
+        images,
+        width,
+        height,
+        scale: DRAFT_SCALE,
+    });
+    RENDER_CACHE.lock().unwrap().insert(render.clone());
+
+    Some(render)
+}
+
+// Renders the pages again at the scale the viewer wants, going round again if the zoom changes
+// while it's working
+fn sharpen_pages(
+    mut render: Arc<RenderedRace>,
+    job: u64,
+    preview: &Preview,
+    main_window_weak: &Weak<MainWindow>,
+) {
+    loop {
+        let scale = preview_scale();
+        if (render.scale - scale).abs() < 0.01 {
+            break;
+        }
+
+        let rendered = render_pdf(render.pdf.clone(), scale, |done, total| {
+            if !is_current(job) {
+                return false;
+            }
+            let progress = 0.3 + 0.7 * done as f32 / total.max(1) as f32;
+            post_preview_progress(main_window_weak, job, true, progress, "Rendering pages");
+
+            true
+        });
+        let (images, width, height) = match rendered {
+            Ok(x) => x,
             Err(e) => {
-                println!("Failed to parse {}, {}", path, e);
+                if is_current(job) {
+                    report_error(format!("Failed to render pages, {}", e));
+                    post_preview_progress(main_window_weak, job, false, 0.0, "");
+                }
+                return;
             }
         };
+
+        render = Arc::new(RenderedRace {
+            key: render.key.clone(),
+            event: render.event.clone(),
+            pdf: render.pdf.clone(),
+            images,
+            width,
+            height,
+            scale,
+        });
+        RENDER_CACHE.lock().unwrap().replace(render.clone());
+
+        let render = render.clone();
+        let preview = preview.clone();
+        let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+            if is_current(job) {
+                show_pages(&main_window, render, &preview, false);
+            }
+        });
     }
+
+    post_preview_progress(main_window_weak, job, false, 1.0, "");
+}
+
+// Re-renders the shown race once the page is drawn at a new size. The viewer scales the pages it
+// already has in the meantime.
+fn start_zoom_job(main_window: &MainWindow, preview: Preview) {
+    // A race still being rendered picks up the new size when it gets to the sharp render
+    if main_window.get_preview_busy() {
+        return;
+    }
+    let render = match preview.cur_render.lock().unwrap().clone() {
+        Some(x) => x,
+        None => return,
+    };
+    if (render.scale - preview_scale()).abs() < 0.01 {
+        return;
+    }
+
+    let job = PREVIEW_JOB.fetch_add(1, Ordering::SeqCst) + 1;
+    set_preview_progress(main_window, true, 0.3, "Rendering pages");
+    let main_window_weak = main_window.as_weak();
+    std::thread::spawn(move || {
+        sharpen_pages(render, job, &preview, &main_window_weak);
+    });
 }
 
 fn set_watcher(watcher_cln: Arc<Mutex<RecommendedWatcher>>, main_window: &MainWindow) {
     let mut watcher = watcher_cln.lock().unwrap();
     // Get paths
-    let settings_data = &mut load_config().unwrap();
+    let settings_data = &mut load_config();
     let paths: &mut Vec<String> = &mut settings_data
         .search_paths
         .iter()
         .map(|x| x.to_string())
         .collect();
+    let recursive = settings_data.recursive_paths.iter().collect::<Vec<_>>();
 
     // Reset watcher
     let main_window_weak = main_window.as_weak();
@@ -436,10 +1845,15 @@ fn set_watcher(watcher_cln: Arc<Mutex<RecommendedWatcher>>, main_window: &MainWi
     })
         .expect("Could not watch directories");
     // Add paths
-    for path in paths {
+    for (i, path) in paths.iter().enumerate() {
         if std::fs::exists(&path).unwrap_or(false) {
+            let mode = if recursive.get(i).copied().unwrap_or(false) {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
             watcher
-                .watch(path.as_ref(), RecursiveMode::NonRecursive)
+                .watch(path.as_ref(), mode)
                 .expect("Failed to watch path");
         }
     }
@@ -462,6 +1876,14 @@ fn gen_sorted_table(
                 4 => x.last_name.cmp(&o.last_name),
                 5 => x.club.cmp(&o.club),
                 6 => cmp_slint_skater_time(&x.time, &o.time),
+                7 => cmp_slint_metric(x.gap_to_leader, o.gap_to_leader),
+                8 => cmp_slint_metric(x.gap_to_previous, o.gap_to_previous),
+                9 => cmp_slint_metric(x.speed_kmh, o.speed_kmh),
+                10 => cmp_slint_metric(x.lap_average, o.lap_average),
+                11 => x
+                    .category
+                    .cmp(&o.category)
+                    .then(x.category_place.cmp(&o.category_place)),
                 // 0 and everything else, sort by place
                 _ => x.place.cmp(&o.place),
             };
@@ -471,7 +1893,10 @@ fn gen_sorted_table(
     {
         let items = Rc::new(VecModel::default());
 
-        let table_row = gen_table_row(r.into());
+        let competitor: CompetitorRow = r.into();
+        let mut table_row = gen_table_row(competitor.clone());
+        table_row.extend(gen_metrics_row(&competitor, MetricColumns::all()));
+        table_row.push(gen_category_cell(&competitor));
 
         for entry in table_row {
             items.push(SharedString::from(entry).into());
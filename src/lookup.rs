@@ -0,0 +1,54 @@
+use crate::parse::{CompetitorRow, EventRow, RaceEvent};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default)]
+pub struct SkaterAppearance {
+    pub path: PathBuf,
+    pub event: EventRow,
+    pub competitor: CompetitorRow,
+}
+
+fn matches_skater(competitor: &CompetitorRow, query: &str) -> bool {
+    if let Ok(id) = query.parse::<u32>() {
+        return competitor.skater_id == Some(id);
+    }
+
+    let query = query.to_lowercase();
+    let full_name = format!("{} {}", competitor.first_name, competitor.last_name).to_lowercase();
+    let reversed_name =
+        format!("{}, {}", competitor.last_name, competitor.first_name).to_lowercase();
+
+    full_name.contains(&query)
+        || reversed_name.contains(&query)
+        || competitor.club.to_lowercase().contains(&query)
+}
+
+// Every race entry across the meet matching a skater ID, or part of a name or club
+pub fn find_skater(events: &Vec<(PathBuf, RaceEvent)>, query: &str) -> Vec<SkaterAppearance> {
+    let query = query.trim();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut out = vec![];
+    for (path, event) in events {
+        for competitor in &event.competitors {
+            if matches_skater(competitor, query) {
+                out.push(SkaterAppearance {
+                    path: path.clone(),
+                    event: event.event.clone(),
+                    competitor: competitor.clone(),
+                });
+            }
+        }
+    }
+    out.sort_by(|x, y| {
+        x.competitor
+            .last_name
+            .cmp(&y.competitor.last_name)
+            .then(x.competitor.first_name.cmp(&y.competitor.first_name))
+            .then(x.event.start_time.cmp(&y.event.start_time))
+    });
+
+    out
+}
@@ -3,13 +3,33 @@
 
 use native_dialog::MessageLevel;
 
+mod auto_process;
+mod categories;
 mod config;
+mod evt;
 mod flag;
+mod history;
 mod interface;
+mod lookup;
+mod mass_start;
+mod meet;
+mod meet_db;
+mod metrics;
+mod output;
 mod parse;
 mod pdf;
 mod print;
+mod progression;
+mod records;
+mod render_cache;
+mod revision;
+mod scan;
+mod search;
+mod stability;
+mod standings;
+mod status;
 mod table_data;
+mod team;
 
 slint::include_modules!();
 
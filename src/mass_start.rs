@@ -0,0 +1,147 @@
+use crate::parse::RaceEvent;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default)]
+pub struct SprintPlacing {
+    pub lap: u32,
+    // Skater IDs in the order they crossed at this sprint
+    pub skater_ids: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PointsTable {
+    pub sprint: Vec<u32>,
+    pub finish: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PointsRow {
+    pub skater_id: u32,
+    pub first_name: String,
+    pub last_name: String,
+    pub place: u8,
+    // Points from each intermediate sprint, in the same order as `MassStartScoring::sprint_laps`
+    pub sprint_points: Vec<u32>,
+    pub finish_points: u32,
+    pub total: u32,
+    pub rank: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MassStartScoring {
+    pub sprint_laps: Vec<u32>,
+    // Ordered by final ranking
+    pub rows: Vec<PointsRow>,
+}
+
+pub fn parse_points(raw: &str) -> Result<Vec<u32>, String> {
+    let mut out = vec![];
+    for points in raw.split(",") {
+        let points = points.trim();
+        if points.is_empty() {
+            continue;
+        }
+        out.push(
+            points
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid points value \"{}\"", points))?,
+        );
+    }
+
+    Ok(out)
+}
+
+// Each non-empty line is "lap,id,id,id..." with skater IDs in the order they took the sprint
+pub fn parse_sprints(raw: &str) -> Result<Vec<SprintPlacing>, String> {
+    let mut out = vec![];
+    for line in raw.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut split = line.split(",").map(|x| x.trim());
+        let lap = split
+            .next()
+            .unwrap_or("")
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid lap in sprint line \"{}\"", line))?;
+
+        let mut skater_ids = vec![];
+        for id in split {
+            if id.is_empty() {
+                continue;
+            }
+            skater_ids.push(
+                id.parse::<u32>()
+                    .map_err(|_| format!("Invalid skater ID \"{}\" in sprint {}", id, lap))?,
+            );
+        }
+
+        out.push(SprintPlacing { lap, skater_ids });
+    }
+    out.sort_by_key(|x| x.lap);
+
+    Ok(out)
+}
+
+pub fn sprints_path(lif_path: &Path) -> PathBuf {
+    lif_path.with_extension("sprints")
+}
+
+pub fn is_mass_start(event: &RaceEvent, lif_path: &Path) -> bool {
+    event.event.event_name.to_lowercase().contains("mass start")
+        || std::fs::exists(sprints_path(lif_path)).unwrap_or(false)
+}
+
+// Totals sprint and finish points for every skater, then ranks by points and breaks ties
+// (including everyone on zero) by finishing place
+pub fn score_mass_start(
+    event: &RaceEvent,
+    sprints: &[SprintPlacing],
+    table: &PointsTable,
+) -> MassStartScoring {
+    let mut rows = vec![];
+    for competitor in &event.competitors {
+        let skater_id = competitor.skater_id.unwrap_or(i32::MAX as u32);
+        let place = competitor.place.unwrap_or(255);
+
+        let sprint_points = sprints
+            .iter()
+            .map(|sprint| {
+                sprint
+                    .skater_ids
+                    .iter()
+                    .position(|x| *x == skater_id)
+                    .and_then(|i| table.sprint.get(i))
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        let finish_points = match place {
+            0 | 255 => 0,
+            _ => table.finish.get(place as usize - 1).copied().unwrap_or(0),
+        };
+
+        rows.push(PointsRow {
+            skater_id,
+            first_name: competitor.first_name.clone(),
+            last_name: competitor.last_name.clone(),
+            place,
+            total: sprint_points.iter().sum::<u32>() + finish_points,
+            sprint_points,
+            finish_points,
+            rank: 0,
+        });
+    }
+
+    rows.sort_by(|x, y| y.total.cmp(&x.total).then(x.place.cmp(&y.place)));
+    for (i, row) in rows.iter_mut().enumerate() {
+        row.rank = i + 1;
+    }
+
+    MassStartScoring {
+        sprint_laps: sprints.iter().map(|x| x.lap).collect(),
+        rows,
+    }
+}
@@ -0,0 +1,20 @@
+use crate::SettingsData;
+use crate::meet_db::load_race;
+use crate::parse::RaceEvent;
+use crate::scan::scan_lif_files;
+use crate::status::report_error;
+use std::path::PathBuf;
+
+// Loads every race of the meet, skipping files that fail to parse
+pub fn load_meet(settings: &SettingsData) -> Vec<(PathBuf, RaceEvent)> {
+    let mut out = vec![];
+    for (path, _) in scan_lif_files(settings) {
+        match load_race(&path) {
+            Ok(event) => out.push((path, event)),
+            Err(e) => report_error(format!("Skipping {}, {}", path.display(), e)),
+        }
+    }
+    out.sort_by(|x, y| x.0.cmp(&y.0));
+
+    out
+}
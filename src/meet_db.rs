@@ -0,0 +1,615 @@
+use crate::flag::{is_lap_count_mismatch, is_time_discrepancy};
+use crate::parse::{CompetitorRow, EventRow, RaceEvent, SkaterTime};
+use crate::revision::{Revision, diff_competitors};
+use crate::status::report_error;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Bumped whenever parsing changes what's stored for a race, so races stored by an older version
+// are parsed again
+const PARSER_VERSION: i64 = 1;
+// The preview, the auto-process worker and the list details can all be using the database at
+// once, so each waits this long for the others before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS races (
+    path TEXT PRIMARY KEY,
+    modified INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    event_code TEXT NOT NULL,
+    round INTEGER,
+    heat INTEGER,
+    event_name TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    distance_m REAL,
+    parser_version INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS competitors (
+    race_path TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    place INTEGER,
+    skater_id INTEGER,
+    lane INTEGER,
+    last_name TEXT NOT NULL,
+    first_name TEXT NOT NULL,
+    club TEXT NOT NULL,
+    time REAL,
+    start_time TEXT NOT NULL,
+    PRIMARY KEY (race_path, idx)
+);
+CREATE INDEX IF NOT EXISTS competitors_skater ON competitors (skater_id);
+CREATE INDEX IF NOT EXISTS competitors_name ON competitors (last_name, first_name);
+CREATE INDEX IF NOT EXISTS competitors_club ON competitors (club);
+CREATE TABLE IF NOT EXISTS splits (
+    race_path TEXT NOT NULL,
+    competitor_idx INTEGER NOT NULL,
+    lap INTEGER NOT NULL,
+    time REAL,
+    PRIMARY KEY (race_path, competitor_idx, lap)
+);
+-- Corrections made by hand. The results tables can't be edited yet, so nothing writes these,
+-- but databases from before keep the ones they have.
+CREATE TABLE IF NOT EXISTS edits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    race_path TEXT NOT NULL,
+    competitor_idx INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    old_value TEXT NOT NULL,
+    new_value TEXT NOT NULL,
+    edited_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS validation (
+    race_path TEXT NOT NULL,
+    competitor_idx INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    acknowledged INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (race_path, competitor_idx, kind)
+);
+CREATE TABLE IF NOT EXISTS outputs (
+    race_path TEXT PRIMARY KEY,
+    pdf_generated_at INTEGER,
+    printed_at INTEGER
+);
+CREATE TABLE IF NOT EXISTS output_files (
+    file_path TEXT PRIMARY KEY,
+    race_path TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    written_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS revisions (
+    race_path TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    changed_at INTEGER NOT NULL,
+    changes TEXT NOT NULL,
+    PRIMARY KEY (race_path, revision)
+);
+";
+
+// Each watched directory is its own meet, kept in the config directory so writing the database
+// doesn't set off the directory watcher
+fn db_path(lif_path: &Path) -> PathBuf {
+    let dir = lif_path
+        .parent()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = dir
+        .chars()
+        .map(|x| if x.is_alphanumeric() { x } else { '_' })
+        .collect::<String>();
+
+    dirs::config_dir()
+        .unwrap()
+        .join("lifpdf_meets")
+        .join(format!("{}.sqlite", name))
+}
+
+pub fn open_meet(lif_path: &Path) -> Result<Connection, String> {
+    let path = db_path(lif_path);
+    if let Some(parent) = path.parent() {
+        if !std::fs::exists(parent).unwrap_or(false) {
+            let _ = std::fs::create_dir_all(parent);
+        }
+    }
+
+    let conn = Connection::open(&path)
+        .map_err(|e| format!("Failed to open meet database {}, {}", path.display(), e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .and_then(|_| conn.execute_batch(SCHEMA))
+        .and_then(|_| migrate(&conn))
+        .map_err(|e| format!("Failed to set up meet database, {}", e))?;
+
+    Ok(conn)
+}
+
+// Brings databases made by earlier versions up to date, tracked with SQLite's user_version
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < 1 {
+        // Races stored before the parser version was kept count as parsed by an older one
+        let has_parser_version: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('races')
+             WHERE name = 'parser_version')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_parser_version {
+            conn.execute_batch(
+                "ALTER TABLE races ADD COLUMN parser_version INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+        conn.execute_batch("PRAGMA user_version = 1;")?;
+    }
+
+    Ok(())
+}
+
+fn file_stamp(path: &Path) -> Result<(i64, i64), String> {
+    let metadata =
+        std::fs::metadata(path).map_err(|e| format!("Failed to read {}, {}", path.display(), e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_millis() as i64)
+        .unwrap_or(0);
+
+    Ok((modified, metadata.len() as i64))
+}
+
+fn time_to_sql(time: Option<SkaterTime>) -> Option<f64> {
+    time.filter(|x| !x.is_no_time()).map(|x| x.total_secs() as f64)
+}
+
+fn time_from_sql(secs: Option<f64>) -> SkaterTime {
+    match secs {
+        Some(x) => SkaterTime::from_secs(x as f32),
+        None => SkaterTime {
+            minutes: 0,
+            seconds: 0,
+            subsecond: -1.0,
+        },
+    }
+}
+
+// Without a stamp this reads whatever version of the race was stored last, by any parser
+fn read_race(
+    conn: &Connection,
+    path: &str,
+    stamp: Option<(i64, i64)>,
+) -> rusqlite::Result<Option<RaceEvent>> {
+    let event = conn
+        .query_row(
+            "SELECT event_code, round, heat, event_name, start_time, distance_m FROM races
+             WHERE path = ?1
+             AND (?2 IS NULL OR (modified = ?2 AND size = ?3 AND parser_version = ?4))",
+            params![path, stamp.map(|x| x.0), stamp.map(|x| x.1), PARSER_VERSION],
+            |row| {
+                Ok(EventRow {
+                    event_code: row.get(0)?,
+                    round: row.get(1)?,
+                    heat: row.get(2)?,
+                    event_name: row.get(3)?,
+                    start_time: row.get(4)?,
+                    distance_m: row.get::<_, Option<f64>>(5)?.map(|x| x as f32),
+                    expected_laps: None,
+                })
+            },
+        )
+        .optional()?;
+
+    let event = match event {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+
+    let mut competitors = conn
+        .prepare(
+            "SELECT place, skater_id, lane, last_name, first_name, club, time, start_time
+             FROM competitors WHERE race_path = ?1 ORDER BY idx",
+        )?
+        .query_map(params![path], |row| {
+            Ok(CompetitorRow {
+                place: row.get(0)?,
+                skater_id: row.get(1)?,
+                lane: row.get(2)?,
+                last_name: row.get(3)?,
+                first_name: row.get(4)?,
+                club: row.get(5)?,
+                time: Some(time_from_sql(row.get(6)?)),
+                start_time: row.get(7)?,
+                ..Default::default()
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut statement = conn.prepare(
+        "SELECT competitor_idx, time FROM splits WHERE race_path = ?1
+         ORDER BY competitor_idx, lap",
+    )?;
+    let splits = statement.query_map(params![path], |row| {
+        Ok((row.get::<_, usize>(0)?, row.get::<_, Option<f64>>(1)?))
+    })?;
+    for split in splits {
+        let (idx, time) = split?;
+        if let Some(competitor) = competitors.get_mut(idx) {
+            competitor.splits.push(time_from_sql(time));
+        }
+    }
+
+    Ok(Some(RaceEvent {
+        event,
+        competitors,
+        ..Default::default()
+    }))
+}
+
+// Run inside the transaction of store_race
+fn write_race(
+    tx: &Connection,
+    path: &str,
+    modified: i64,
+    size: i64,
+    event: &RaceEvent,
+) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT OR REPLACE INTO races
+         (path, modified, size, event_code, round, heat, event_name, start_time, distance_m,
+          parser_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            path,
+            modified,
+            size,
+            event.event.event_code,
+            event.event.round,
+            event.event.heat,
+            event.event.event_name,
+            event.event.start_time,
+            event.event.distance_m.map(|x| x as f64),
+            PARSER_VERSION
+        ],
+    )?;
+    tx.execute("DELETE FROM competitors WHERE race_path = ?1", params![path])?;
+    tx.execute("DELETE FROM splits WHERE race_path = ?1", params![path])?;
+
+    // Acknowledged flags that are still raised keep their acknowledgement
+    let mut raised = vec![];
+    for (idx, competitor) in event.competitors.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO competitors
+             (race_path, idx, place, skater_id, lane, last_name, first_name, club, time, start_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                path,
+                idx,
+                competitor.place,
+                competitor.skater_id,
+                competitor.lane,
+                competitor.last_name,
+                competitor.first_name,
+                competitor.club,
+                time_to_sql(competitor.time),
+                competitor.start_time
+            ],
+        )?;
+
+        for (lap, split) in competitor.splits.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO splits (race_path, competitor_idx, lap, time) VALUES (?1, ?2, ?3, ?4)",
+                params![path, idx, lap, time_to_sql(Some(*split))],
+            )?;
+        }
+
+        if let Some(time) = competitor.time {
+            if is_time_discrepancy(time, &competitor.splits) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO validation (race_path, competitor_idx, kind)
+                     VALUES (?1, ?2, 'time_discrepancy')",
+                    params![path, idx],
+                )?;
+                raised.push(idx);
+            }
+        }
+    }
+
+    let mut statement = tx.prepare(
+        "SELECT competitor_idx FROM validation
+         WHERE race_path = ?1 AND kind = 'time_discrepancy'",
+    )?;
+    let stale = statement
+        .query_map(params![path], |row| row.get::<_, usize>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|x| !raised.contains(x))
+        .collect::<Vec<_>>();
+    drop(statement);
+    for idx in stale {
+        tx.execute(
+            "DELETE FROM validation
+             WHERE race_path = ?1 AND competitor_idx = ?2 AND kind = 'time_discrepancy'",
+            params![path, idx],
+        )?;
+    }
+
+    Ok(())
+}
+
+// The file stamp and parser version the race was stored with
+fn stored_stamp(conn: &Connection, path: &str) -> rusqlite::Result<Option<((i64, i64), i64)>> {
+    conn.query_row(
+        "SELECT modified, size, parser_version FROM races WHERE path = ?1",
+        params![path],
+        |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)),
+    )
+    .optional()
+}
+
+// Stores a freshly parsed race, recording a revision if it changed since it was stored. The
+// preview, the list details and auto-process can all parse the same changed file at once, so the
+// check, the revision and the write happen in one transaction, with the write lock taken up
+// front, and only the first of them records the revision.
+fn store_race(
+    conn: &mut Connection,
+    path: &str,
+    modified: i64,
+    size: i64,
+    event: &RaceEvent,
+) -> rusqlite::Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    match stored_stamp(&tx, path)? {
+        // Stored by another reader while this one was parsing
+        Some((stamp, version)) if stamp == (modified, size) && version == PARSER_VERSION => {
+            return Ok(());
+        }
+        // The same file parsed again by a newer version hasn't changed
+        Some((stamp, _)) if stamp == (modified, size) => {}
+        _ => record_revision(&tx, path, event)?,
+    }
+    write_race(&tx, path, modified, size, event)?;
+
+    tx.commit()
+}
+
+fn was_published(conn: &Connection, path: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM outputs WHERE race_path = ?1
+             AND (pdf_generated_at IS NOT NULL OR printed_at IS NOT NULL))
+         OR EXISTS (SELECT 1 FROM revisions WHERE race_path = ?1)",
+        params![path],
+        |row| row.get(0),
+    )
+}
+
+// A race that was already put out and then changes, usually after a photo finish correction,
+// gets a new revision with what changed. Its outputs are cleared so the list shows it needs
+// generating and printing again.
+fn record_revision(conn: &Connection, path: &str, event: &RaceEvent) -> rusqlite::Result<()> {
+    let previous = match read_race(conn, path, None)? {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    if !was_published(conn, path)? {
+        return Ok(());
+    }
+
+    let changes = diff_competitors(&previous.competitors, &event.competitors);
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let number: u32 = conn.query_row(
+        "SELECT COALESCE(MAX(revision), 1) + 1 FROM revisions WHERE race_path = ?1",
+        params![path],
+        |row| row.get(0),
+    )?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or(0);
+    conn.execute(
+        "INSERT INTO revisions (race_path, revision, changed_at, changes)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![path, number, now, changes.join("\n")],
+    )?;
+    conn.execute("DELETE FROM outputs WHERE race_path = ?1", params![path])?;
+
+    Ok(())
+}
+
+// Reads the race from the meet database, only parsing the LIF file if it changed or was parsed
+// by an older version since it was last stored. Falls back to parsing if the database can't be
+// used.
+pub fn load_race(path: &Path) -> Result<RaceEvent, String> {
+    let (modified, size) = file_stamp(path)?;
+    let key = path.to_string_lossy().to_string();
+
+    let mut conn = match open_meet(path) {
+        Ok(x) => x,
+        Err(e) => {
+            report_error(e);
+            return RaceEvent::read_lif_file(path);
+        }
+    };
+
+    match read_race(&conn, &key, Some((modified, size))) {
+        Ok(Some(event)) => return Ok(event),
+        Ok(None) => {}
+        Err(e) => report_error(format!("Failed to read {} from meet database, {}", key, e)),
+    }
+
+    let event = RaceEvent::read_lif_file(path)?;
+    if let Err(e) = store_race(&mut conn, &key, modified, size, &event) {
+        report_error(format!("Failed to store {} in meet database, {}", key, e));
+    }
+
+    Ok(event)
+}
+
+// The expected lap count comes from the track setting rather than the file, so lap counts are
+// checked each time the race is prepared instead of when it's stored. Competitors are in the
+// order they were read from the file.
+pub fn flag_lap_count_mismatches(path: &Path, event: &RaceEvent) -> Result<(), String> {
+    let key = path.to_string_lossy().to_string();
+    let mut conn = open_meet(path)?;
+
+    let mut flag = || -> rusqlite::Result<()> {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "DELETE FROM validation
+             WHERE race_path = ?1 AND kind = 'lap_count_mismatch' AND competitor_idx >= ?2",
+            params![key, event.competitors.len()],
+        )?;
+        for (idx, competitor) in event.competitors.iter().enumerate() {
+            if is_lap_count_mismatch(&competitor.splits, event.event.expected_laps) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO validation (race_path, competitor_idx, kind)
+                     VALUES (?1, ?2, 'lap_count_mismatch')",
+                    params![key, idx],
+                )?;
+            } else {
+                tx.execute(
+                    "DELETE FROM validation WHERE race_path = ?1 AND competitor_idx = ?2
+                     AND kind = 'lap_count_mismatch'",
+                    params![key, idx],
+                )?;
+            }
+        }
+        tx.commit()
+    };
+
+    flag().map_err(|e| format!("Failed to check lap counts of {}, {}", key, e))
+}
+
+pub fn unacknowledged_flags(path: &Path) -> usize {
+    let key = path.to_string_lossy().to_string();
+    open_meet(path)
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM validation WHERE race_path = ?1 AND acknowledged = 0",
+                params![key],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|e| e.to_string())
+        })
+        .map(|x| x as usize)
+        .unwrap_or(0)
+}
+
+pub fn acknowledge_flags(path: &Path) -> Result<(), String> {
+    let key = path.to_string_lossy().to_string();
+    open_meet(path)?
+        .execute(
+            "UPDATE validation SET acknowledged = 1 WHERE race_path = ?1",
+            params![key],
+        )
+        .map(|_| ())
+        .map_err(|e| format!("Failed to acknowledge flags, {}", e))
+}
+
+fn mark_output(path: &Path, column: &str) -> Result<(), String> {
+    let key = path.to_string_lossy().to_string();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or(0);
+    open_meet(path)?
+        .execute(
+            &format!(
+                "INSERT INTO outputs (race_path, {0}) VALUES (?1, ?2)
+                 ON CONFLICT (race_path) DO UPDATE SET {0} = ?2",
+                column
+            ),
+            params![key, now],
+        )
+        .map(|_| ())
+        .map_err(|e| format!("Failed to record output of {}, {}", key, e))
+}
+
+pub fn mark_pdf_generated(path: &Path) -> Result<(), String> {
+    mark_output(path, "pdf_generated_at")
+}
+
+pub fn mark_printed(path: &Path) -> Result<(), String> {
+    mark_output(path, "printed_at")
+}
+
+// Whether a PDF has been generated and whether the race has been printed
+pub fn output_status(path: &Path) -> (bool, bool) {
+    let key = path.to_string_lossy().to_string();
+    open_meet(path)
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT pdf_generated_at IS NOT NULL, printed_at IS NOT NULL FROM outputs
+                 WHERE race_path = ?1",
+                params![key],
+                |row| Ok((row.get::<_, bool>(0)?, row.get::<_, bool>(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+        })
+        .ok()
+        .flatten()
+        .unwrap_or((false, false))
+}
+
+pub fn latest_revision(path: &Path) -> Option<Revision> {
+    let key = path.to_string_lossy().to_string();
+    open_meet(path)
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT revision, changed_at, changes FROM revisions WHERE race_path = ?1
+                 ORDER BY revision DESC LIMIT 1",
+                params![key],
+                |row| {
+                    Ok(Revision {
+                        number: row.get(0)?,
+                        changed_at: row.get::<_, i64>(1)? as u64,
+                        changes: row
+                            .get::<_, String>(2)?
+                            .lines()
+                            .map(|x| x.to_string())
+                            .collect(),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+        })
+        .ok()
+        .flatten()
+}
+
+// Remembers which race and revision an output file holds, so archiving can name it
+pub fn record_output_file(path: &Path, file: &Path, revision: u32) -> Result<(), String> {
+    let key = path.to_string_lossy().to_string();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or(0);
+    open_meet(path)?
+        .execute(
+            "INSERT OR REPLACE INTO output_files (file_path, race_path, revision, written_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![file.to_string_lossy().to_string(), key, revision, now],
+        )
+        .map(|_| ())
+        .map_err(|e| format!("Failed to record output of {}, {}", key, e))
+}
+
+// The race and revision last written to an output file, if it was written by this meet
+pub fn output_file_owner(path: &Path, file: &Path) -> Option<(String, u32)> {
+    open_meet(path)
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT race_path, revision FROM output_files WHERE file_path = ?1",
+                params![file.to_string_lossy().to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+        })
+        .ok()
+        .flatten()
+}
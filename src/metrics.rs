@@ -0,0 +1,168 @@
+use crate::parse::{RaceEvent, SkaterTime, TrackType};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Metrics {
+    pub gap_to_leader: Option<f32>,
+    pub gap_to_previous: Option<f32>,
+    pub speed_kmh: Option<f32>,
+    pub lap_average: Option<f32>,
+}
+
+// Fills in the derived columns for every competitor with a time. Gaps are measured against the
+// skaters ahead on time. The lap average is over full laps of the track when the distance is
+// known, and over the recorded splits otherwise.
+pub fn apply_metrics(event: &mut RaceEvent, track: TrackType) {
+    let distance_m = event.event.distance_m;
+    let laps = distance_m.map(|x| x / track.lap_length_m());
+
+    let mut timed = event
+        .competitors
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| match x.time {
+            Some(t) if !t.is_no_time() => Some((i, t.total_secs())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    timed.sort_by(|x, y| x.1.total_cmp(&y.1));
+
+    for competitor in event.competitors.iter_mut() {
+        competitor.metrics = Metrics::default();
+    }
+
+    let leader = timed.first().map(|x| x.1);
+    let mut previous = None;
+    for (i, secs) in timed {
+        let competitor = &mut event.competitors[i];
+
+        competitor.metrics.gap_to_leader = leader.map(|x| secs - x);
+        competitor.metrics.gap_to_previous = previous.map(|x| secs - x);
+        competitor.metrics.speed_kmh = distance_m
+            .filter(|_| secs > 0.0)
+            .map(|x| x / secs * 3.6);
+        competitor.metrics.lap_average = match laps {
+            Some(x) => Some(secs / x),
+            None if competitor.splits.len() > 0 => Some(secs / competitor.splits.len() as f32),
+            None => None,
+        };
+
+        previous = Some(secs);
+    }
+}
+
+pub fn format_gap(gap: Option<f32>) -> String {
+    match gap {
+        Some(x) if x > 0.0 => format!("+{}", SkaterTime::from_secs(x)),
+        Some(_) => "-".to_string(),
+        None => String::new(),
+    }
+}
+
+pub fn format_speed(speed: Option<f32>) -> String {
+    speed.map(|x| format!("{:.2}", x)).unwrap_or_default()
+}
+
+pub fn format_lap_average(lap_average: Option<f32>) -> String {
+    lap_average
+        .map(|x| SkaterTime::from_secs(x).to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{CompetitorRow, EventRow};
+
+    // Competitors in file order, with no time for a DNF
+    fn race(distance_m: Option<f32>, times: &[Option<f32>]) -> RaceEvent {
+        let no_time = SkaterTime {
+            subsecond: -1.0,
+            ..Default::default()
+        };
+        RaceEvent {
+            event: EventRow {
+                distance_m,
+                ..Default::default()
+            },
+            competitors: times
+                .iter()
+                .map(|x| CompetitorRow {
+                    time: Some(x.map(SkaterTime::from_secs).unwrap_or(no_time)),
+                    splits: vec![SkaterTime::default(); 5],
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn close(value: Option<f32>, expected: f32) -> bool {
+        value.map(|x| (x - expected).abs() < 0.001).unwrap_or(false)
+    }
+
+    #[test]
+    fn short_track_500m() {
+        let mut event = race(Some(500.0), &[Some(45.5), Some(45.0)]);
+        apply_metrics(&mut event, TrackType::ShortTrack);
+
+        let winner = event.competitors[1].metrics;
+        assert!(close(winner.gap_to_leader, 0.0));
+        assert_eq!(winner.gap_to_previous, None);
+        assert!(close(winner.speed_kmh, 40.0));
+        // 4.5 laps of 111.12 m
+        assert!(close(winner.lap_average, 45.0 * 111.12 / 500.0));
+
+        let second = event.competitors[0].metrics;
+        assert!(close(second.gap_to_leader, 0.5));
+        assert!(close(second.gap_to_previous, 0.5));
+    }
+
+    #[test]
+    fn long_track_500m() {
+        let mut event = race(Some(500.0), &[Some(40.0)]);
+        apply_metrics(&mut event, TrackType::LongTrack);
+
+        let metrics = event.competitors[0].metrics;
+        assert!(close(metrics.speed_kmh, 45.0));
+        // 1.25 laps of 400 m
+        assert!(close(metrics.lap_average, 32.0));
+    }
+
+    #[test]
+    fn missing_distance_averages_the_splits() {
+        let mut event = race(None, &[Some(45.0)]);
+        apply_metrics(&mut event, TrackType::ShortTrack);
+
+        let metrics = event.competitors[0].metrics;
+        assert_eq!(metrics.speed_kmh, None);
+        assert!(close(metrics.lap_average, 9.0));
+
+        event.competitors[0].splits.clear();
+        apply_metrics(&mut event, TrackType::ShortTrack);
+        assert_eq!(event.competitors[0].metrics.lap_average, None);
+    }
+
+    #[test]
+    fn dnf_rows_are_left_empty() {
+        let mut event = race(Some(500.0), &[None, Some(46.0), Some(45.0)]);
+        event.competitors[0].metrics.speed_kmh = Some(1.0);
+        apply_metrics(&mut event, TrackType::ShortTrack);
+
+        let dnf = event.competitors[0].metrics;
+        assert_eq!(dnf.gap_to_leader, None);
+        assert_eq!(dnf.gap_to_previous, None);
+        assert_eq!(dnf.speed_kmh, None);
+        assert_eq!(dnf.lap_average, None);
+
+        // Gaps skip over the DNF
+        assert!(close(event.competitors[1].metrics.gap_to_previous, 1.0));
+    }
+
+    #[test]
+    fn gaps_are_formatted_as_times() {
+        assert_eq!(format_gap(Some(0.5)), "+0.500");
+        assert_eq!(format_gap(Some(0.0)), "-");
+        assert_eq!(format_gap(None), "");
+        assert_eq!(format_speed(Some(40.0)), "40.00");
+    }
+}
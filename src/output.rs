@@ -0,0 +1,238 @@
+use crate::history::{civil_date, now_secs, race_date};
+use crate::parse::RaceEvent;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputPolicy {
+    Overwrite,
+    // Later revisions of a race are written next to the first as "_rev2", "_rev3" and so on
+    Revisions,
+    // The file being replaced is moved into an "archive" folder first
+    Archive,
+}
+
+impl OutputPolicy {
+    // Matches the order of the policy combo box in settings
+    pub fn from_index(index: i32) -> Result<Self, String> {
+        match index {
+            0 => Ok(OutputPolicy::Overwrite),
+            1 => Ok(OutputPolicy::Revisions),
+            2 => Ok(OutputPolicy::Archive),
+            _ => Err(format!("Unknown output policy {}", index)),
+        }
+    }
+}
+
+fn compact_timestamp(unix_secs: u64) -> String {
+    let (year, month, day) = civil_date(unix_secs);
+    let secs_of_day = unix_secs % 86400;
+    format!(
+        "{}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+// Drops characters that aren't allowed in file names on any platform
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|x| match x {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            x if x.is_control() => '_',
+            x => x,
+        })
+        .collect::<String>()
+        .trim()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+// Fills in {event_code}, {event_name}, {round}, {heat}, {timestamp} and {revision}, leaving out
+// the extension. The timestamp is `written`, when the race's file was written, so writing the
+// same race out again gives the same name.
+pub fn render_file_name(template: &str, event: &RaceEvent, written: u64) -> String {
+    let option = |x: Option<u8>| x.map(|x| x.to_string()).unwrap_or_default();
+    let name = template
+        .replace("{event_code}", &event.event.event_code)
+        .replace("{event_name}", &event.event.event_name)
+        .replace("{round}", &option(event.event.round))
+        .replace("{heat}", &option(event.event.heat))
+        .replace("{timestamp}", &compact_timestamp(written))
+        .replace(
+            "{revision}",
+            &event
+                .revision
+                .as_ref()
+                .map(|x| x.number)
+                .unwrap_or(1)
+                .to_string(),
+        );
+
+    // Placeholders left empty, like the round of a race without rounds, don't leave separators
+    // hanging off the ends
+    match sanitize(&name).trim_matches(['_', '-', ' ', '.']) {
+        x if x.is_empty() => sanitize(&event.event.event_code),
+        x => x.to_string(),
+    }
+}
+
+// Where the PDF of a revision goes under the policy
+pub fn output_path(dir: &Path, name: &str, revision: u32, policy: OutputPolicy) -> PathBuf {
+    match policy {
+        OutputPolicy::Revisions if revision > 1 => {
+            dir.join(format!("{}_rev{}.pdf", name, revision))
+        }
+        _ => dir.join(format!("{}.pdf", name)),
+    }
+}
+
+// Moves a file about to be replaced into the archive folder beside it, named after the revision
+// it held when that's known and the time it was written otherwise
+pub fn archive_existing(target: &Path, old_revision: Option<u32>) -> Result<(), String> {
+    let dir = match target.parent() {
+        Some(x) => x.join("archive"),
+        None => return Ok(()),
+    };
+    if !std::fs::exists(&dir).unwrap_or(false) {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}, {}", dir.display(), e))?;
+    }
+
+    let stem = target
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let archived = match old_revision {
+        Some(x) => dir.join(format!("{}_rev{}.pdf", stem, x)),
+        None => dir.join(format!(
+            "{}_{}.pdf",
+            stem,
+            compact_timestamp(race_date(target).unwrap_or_else(now_secs))
+        )),
+    };
+
+    std::fs::rename(target, &archived).map_err(|e| {
+        format!(
+            "Failed to archive {} to {}, {}",
+            target.display(),
+            archived.display(),
+            e
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::EventRow;
+    use crate::revision::Revision;
+
+    // 2024-02-29 13:05:09 UTC
+    const WRITTEN: u64 = 1709164800 + 13 * 3600 + 5 * 60 + 9;
+
+    fn event(round: Option<u8>, revision: Option<u32>) -> RaceEvent {
+        RaceEvent {
+            event: EventRow {
+                event_code: "12".to_string(),
+                round,
+                heat: Some(3),
+                event_name: "500m Open: Men/Women".to_string(),
+                ..Default::default()
+            },
+            revision: revision.map(|number| Revision {
+                number,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn policy_follows_the_settings_combo() {
+        assert_eq!(OutputPolicy::from_index(0), Ok(OutputPolicy::Overwrite));
+        assert_eq!(OutputPolicy::from_index(1), Ok(OutputPolicy::Revisions));
+        assert_eq!(OutputPolicy::from_index(2), Ok(OutputPolicy::Archive));
+        assert!(OutputPolicy::from_index(3).is_err());
+        assert!(OutputPolicy::from_index(-1).is_err());
+    }
+
+    #[test]
+    fn file_name_fills_in_placeholders() {
+        assert_eq!(
+            render_file_name(
+                "{event_code}-{round}-{heat} {event_name} {timestamp} r{revision}",
+                &event(Some(1), Some(2)),
+                WRITTEN
+            ),
+            "12-1-3 500m Open_ Men_Women 20240229-130509 r2"
+        );
+        assert_eq!(
+            render_file_name("{event_code}_r{revision}", &event(None, None), WRITTEN),
+            "12_r1"
+        );
+    }
+
+    #[test]
+    fn file_name_drops_empty_placeholders_at_the_ends() {
+        assert_eq!(
+            render_file_name("{event_code}-{heat}-{round}", &event(None, None), WRITTEN),
+            "12-3"
+        );
+        assert_eq!(
+            render_file_name("{round}", &event(None, None), WRITTEN),
+            "12"
+        );
+    }
+
+    #[test]
+    fn revisions_get_their_own_file_only_under_that_policy() {
+        let dir = Path::new("out");
+        assert_eq!(
+            output_path(dir, "12-1-3", 1, OutputPolicy::Revisions),
+            dir.join("12-1-3.pdf")
+        );
+        assert_eq!(
+            output_path(dir, "12-1-3", 2, OutputPolicy::Revisions),
+            dir.join("12-1-3_rev2.pdf")
+        );
+        assert_eq!(
+            output_path(dir, "12-1-3", 2, OutputPolicy::Overwrite),
+            dir.join("12-1-3.pdf")
+        );
+        assert_eq!(
+            output_path(dir, "12-1-3", 2, OutputPolicy::Archive),
+            dir.join("12-1-3.pdf")
+        );
+    }
+
+    #[test]
+    fn replaced_files_are_moved_into_the_archive() {
+        let dir = std::env::temp_dir().join(format!("lifpdf_output_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("12-1-3.pdf");
+
+        std::fs::write(&target, "first").unwrap();
+        archive_existing(&target, Some(1)).unwrap();
+        assert!(!std::fs::exists(&target).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("archive").join("12-1-3_rev1.pdf")).unwrap(),
+            "first"
+        );
+
+        // Without a known revision, the archived copy is named after when it was written
+        std::fs::write(&target, "second").unwrap();
+        let written = race_date(&target).unwrap();
+        archive_existing(&target, None).unwrap();
+        let archived = dir
+            .join("archive")
+            .join(format!("12-1-3_{}.pdf", compact_timestamp(written)));
+        assert_eq!(std::fs::read_to_string(archived).unwrap(), "second");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
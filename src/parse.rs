@@ -1,13 +1,111 @@
+use crate::history::BestMark;
+use crate::mass_start::MassStartScoring;
+use crate::metrics::Metrics;
+use crate::progression::Qualification;
+use crate::records::BrokenRecord;
+use crate::revision::Revision;
+use crate::team::TeamRow;
 use crate::{SlintCompetitorRow, SlintEventRow, SlintRaceEvent, SlintSkaterTime};
 use slint::{Model, VecModel};
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::io::Read;
+use std::path::Path;
 
 #[derive(Clone, Debug, Default)]
 pub struct EventRow {
     pub event_code: String,
+    pub round: Option<u8>,
+    pub heat: Option<u8>,
     pub event_name: String,
     pub start_time: String,
+    pub distance_m: Option<f32>,
+    // Timing splits expected for the distance, counting a partial opening lap as one
+    pub expected_laps: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackType {
+    LongTrack,
+    ShortTrack,
+}
+
+impl TrackType {
+    // Matches the order of the track type combo box in settings
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            1 => TrackType::ShortTrack,
+            _ => TrackType::LongTrack,
+        }
+    }
+
+    pub fn lap_length_m(&self) -> f32 {
+        match self {
+            TrackType::LongTrack => 400.0,
+            TrackType::ShortTrack => 111.12,
+        }
+    }
+}
+
+impl EventRow {
+    pub fn infer_laps(&mut self, track: TrackType) {
+        self.expected_laps = self
+            .distance_m
+            .map(|x| (x / track.lap_length_m()).ceil() as u32);
+    }
+}
+
+// Picks a distance out of text like "500m", "1500 m", "3 km" or "1.5k". A number without a unit
+// is taken as metres if nothing with a unit is found, as long as it's a word of its own and at
+// least 100, so "U17" or "Group 2" aren't mistaken for one.
+pub fn parse_distance(text: &str) -> Option<f32> {
+    let chars = text.to_lowercase().chars().collect::<Vec<_>>();
+    let mut bare = None;
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+
+        let starts_word = i == 0 || !chars[i - 1].is_alphanumeric();
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+            i += 1;
+        }
+        let number = match chars[start..i]
+            .iter()
+            .collect::<String>()
+            .parse::<f32>()
+            .ok()
+            .filter(|x| *x > 0.0)
+        {
+            Some(x) => x,
+            None => continue,
+        };
+
+        // Only whole words count as units, so "500mm", "Team" or wind in "m/s" don't match
+        let unit = chars[i..]
+            .iter()
+            .skip_while(|x| **x == ' ')
+            .take_while(|x| x.is_alphanumeric() || **x == '/')
+            .collect::<String>();
+        match unit.as_str() {
+            "m" => return Some(number),
+            "k" | "km" => return Some(number * 1000.0),
+            _ => {}
+        }
+
+        let ends_word = chars
+            .get(i)
+            .map(|x| !x.is_alphanumeric() && *x != '/')
+            .unwrap_or(true);
+        if bare.is_none() && starts_word && ends_word && number >= 100.0 {
+            bare = Some(number);
+        }
+    }
+
+    bare
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -42,10 +140,25 @@ impl std::ops::Add for SkaterTime {
 
 impl SkaterTime {
     pub fn absolute_difference_secs(&self, other: Self) -> f32 {
-        let first_float = self.minutes as f32 * 60.0 + self.seconds as f32 + self.subsecond;
-        let second_float = other.minutes as f32 * 60.0 + other.seconds as f32 + other.subsecond;
+        (self.total_secs() - other.total_secs()).abs()
+    }
+
+    pub fn from_secs(secs: f32) -> Self {
+        let whole = secs.max(0.0).floor();
+        Self {
+            minutes: whole as u32 / 60,
+            seconds: whole as u32 % 60,
+            subsecond: secs.max(0.0) - whole,
+        }
+    }
 
-        (first_float - second_float).abs()
+    pub fn total_secs(&self) -> f32 {
+        self.minutes as f32 * 60.0 + self.seconds as f32 + self.subsecond
+    }
+
+    // parse_time marks DNFs and missing times with a negative subsecond
+    pub fn is_no_time(&self) -> bool {
+        self.subsecond < 0.0
     }
 }
 
@@ -104,6 +217,15 @@ pub struct CompetitorRow {
     pub time: Option<SkaterTime>,
     pub splits: Vec<SkaterTime>,
     pub start_time: String,
+    pub qualification: Qualification,
+    pub metrics: Metrics,
+    pub best_mark: BestMark,
+    // Codes of the records this time beat, e.g. TR or NR
+    pub records_broken: Vec<String>,
+    pub gender: String,
+    pub category: String,
+    // Place by time among skaters of the same category
+    pub category_place: Option<u8>,
 }
 
 impl Into<CompetitorRow> for SlintCompetitorRow {
@@ -118,17 +240,93 @@ impl Into<CompetitorRow> for SlintCompetitorRow {
             time: Some(self.time.into()),
             splits: self.splits.iter().map(|x| x.into()).collect::<Vec<_>>(),
             start_time: self.start_time.into(),
+            qualification: Qualification::from_mark(&self.qualification),
+            metrics: Metrics {
+                gap_to_leader: slint_metric(self.gap_to_leader),
+                gap_to_previous: slint_metric(self.gap_to_previous),
+                speed_kmh: slint_metric(self.speed_kmh),
+                lap_average: slint_metric(self.lap_average),
+            },
+            best_mark: BestMark::from_mark(&self.best_mark),
+            records_broken: self
+                .records_broken
+                .split_whitespace()
+                .map(|x| x.to_string())
+                .collect(),
+            gender: self.gender.into(),
+            category: self.category.into(),
+            category_place: match self.category_place {
+                0 => None,
+                x => Some(x as u8),
+            },
         }
     }
 }
 
+// Slint has no optional floats, so missing metrics cross over as -1
+fn slint_metric(value: f32) -> Option<f32> {
+    if value < 0.0 { None } else { Some(value) }
+}
+
+pub fn cmp_slint_metric(first: f32, other: f32) -> Ordering {
+    // Missing values sort after everything else
+    let first = if first < 0.0 { f32::MAX } else { first };
+    let other = if other < 0.0 { f32::MAX } else { other };
+    first.total_cmp(&other)
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RaceEvent {
     pub event: EventRow,
     pub competitors: Vec<CompetitorRow>,
+    pub next_round: Vec<Vec<CompetitorRow>>,
+    pub mass_start: Option<MassStartScoring>,
+    pub teams: Vec<TeamRow>,
+    pub broken_records: Vec<BrokenRecord>,
+    // Only set once a published race has been changed
+    pub revision: Option<Revision>,
+}
+
+// Splits a comma separated line, keeping quoted commas and dropping the quotes
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    let mut out = vec![];
+    let mut cur_text = String::new();
+    let mut inside_quote = false;
+    for c in line.chars() {
+        match c {
+            '"' => inside_quote = !inside_quote,
+            ',' if !inside_quote => {
+                out.push(cur_text.trim().to_string());
+                cur_text = String::new();
+            }
+            _ => cur_text.push(c),
+        }
+    }
+    out.push(cur_text.trim().to_string());
+
+    out
+}
+
+pub fn is_lif_file_name(name: &str) -> bool {
+    name.to_lowercase().ends_with(".lif")
 }
 
 impl RaceEvent {
+    pub fn read_lif_file(path: &Path) -> Result<Self, String> {
+        let mut f = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}, {}", path.display(), e))?;
+        let mut buffer = Vec::new();
+        f.read_to_end(&mut buffer)
+            .map_err(|e| format!("Failed to read {}, {}", path.display(), e))?;
+
+        let mut file_contents = String::new();
+        for byte in buffer {
+            file_contents.push(byte as char);
+        }
+
+        Self::parse_lif(file_contents, path.to_string_lossy().to_string())
+    }
+
     pub fn parse_lif(raw: String, file_name: String) -> Result<Self, String> {
         let lines = raw.lines().collect::<Vec<_>>();
 
@@ -143,6 +341,12 @@ impl RaceEvent {
                 .first()
                 .ok_or("Event code not found")?
                 .to_string(),
+            round: first_line_split
+                .get(1)
+                .and_then(|x| x.trim().parse::<u8>().ok()),
+            heat: first_line_split
+                .get(2)
+                .and_then(|x| x.trim().parse::<u8>().ok()),
             event_name: first_line_split
                 .get(3)
                 .ok_or("Event name not found")?
@@ -151,6 +355,14 @@ impl RaceEvent {
                 .last()
                 .ok_or("Start time not found")?
                 .to_string(),
+            // Prefer a distance given elsewhere in the header over one in the event name
+            distance_m: first_line_split
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i > 3 && *i < first_line_split.len() - 1)
+                .find_map(|(_, x)| parse_distance(x))
+                .or_else(|| parse_distance(first_line_split.get(3).unwrap_or(&""))),
+            expected_laps: None,
         };
 
         // Assume each subsequent line is competitor data and parse accordingly
@@ -239,12 +451,24 @@ impl RaceEvent {
                     .get(11)
                     .ok_or("Missing start time")?
                     .to_string(),
+                qualification: Qualification::None,
+                metrics: Metrics::default(),
+                best_mark: BestMark::None,
+                records_broken: vec![],
+                gender: String::new(),
+                category: String::new(),
+                category_place: None,
             })
         }
 
         Ok(Self {
             event: event_row,
             competitors: competitor_entries,
+            next_round: vec![],
+            mass_start: None,
+            teams: vec![],
+            broken_records: vec![],
+            revision: None,
         })
     }
 }
@@ -278,6 +502,16 @@ impl Into<SlintRaceEvent> for RaceEvent {
                         .collect::<Vec<_>>(),
                 )),
                 start_time: competitor.start_time.into(),
+                qualification: competitor.qualification.mark().into(),
+                gap_to_leader: competitor.metrics.gap_to_leader.unwrap_or(-1.0),
+                gap_to_previous: competitor.metrics.gap_to_previous.unwrap_or(-1.0),
+                speed_kmh: competitor.metrics.speed_kmh.unwrap_or(-1.0),
+                lap_average: competitor.metrics.lap_average.unwrap_or(-1.0),
+                best_mark: competitor.best_mark.mark().into(),
+                records_broken: competitor.records_broken.join(" ").into(),
+                gender: competitor.gender.into(),
+                category: competitor.category.into(),
+                category_place: competitor.category_place.unwrap_or(0) as i32,
                 time: SlintSkaterTime {
                     minutes: time.minutes as i32,
                     seconds: time.seconds as i32,
@@ -292,12 +526,14 @@ impl Into<SlintRaceEvent> for RaceEvent {
                 event_code: self.event.event_code.into(),
                 event_name: self.event.event_name.into(),
                 start_time: self.event.start_time.into(),
+                distance_m: self.event.distance_m.unwrap_or(-1.0),
+                expected_laps: self.event.expected_laps.map(|x| x as i32).unwrap_or(-1),
             },
         }
     }
 }
 
-fn parse_time(time: String) -> Result<SkaterTime, String> {
+pub fn parse_time(time: String) -> Result<SkaterTime, String> {
     let mut out = SkaterTime::default();
     let mut cur_digit = String::new();
 
@@ -339,3 +575,37 @@ fn parse_time(time: String) -> Result<SkaterTime, String> {
 
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_with_unit() {
+        assert_eq!(parse_distance("500m"), Some(500.0));
+        assert_eq!(parse_distance("1500 m"), Some(1500.0));
+        assert_eq!(parse_distance("Ladies 1000M A"), Some(1000.0));
+        assert_eq!(parse_distance("3 km"), Some(3000.0));
+        assert_eq!(parse_distance("1.5k"), Some(1500.0));
+        assert_eq!(parse_distance("Mass Start 1.5 KM"), Some(1500.0));
+    }
+
+    #[test]
+    fn distance_without_unit() {
+        assert_eq!(parse_distance("3000"), Some(3000.0));
+        assert_eq!(parse_distance("Men 3000 Group 2"), Some(3000.0));
+        // A distance with a unit wins over a bare number before it
+        assert_eq!(parse_distance("Race 101 500m"), Some(500.0));
+    }
+
+    #[test]
+    fn not_a_distance() {
+        assert_eq!(parse_distance(""), None);
+        assert_eq!(parse_distance("Team Pursuit"), None);
+        assert_eq!(parse_distance("Group 2"), None);
+        assert_eq!(parse_distance("U17 Boys"), None);
+        assert_eq!(parse_distance("500mm"), None);
+        assert_eq!(parse_distance("1.2 m/s"), None);
+        assert_eq!(parse_distance("0m"), None);
+    }
+}
@@ -1,15 +1,26 @@
-use crate::flag::is_time_discrepancy;
+use crate::categories::categories_in;
+use crate::flag::{is_lap_count_mismatch, is_time_discrepancy};
+use crate::lookup::SkaterAppearance;
+use crate::mass_start::MassStartScoring;
 use crate::parse::*;
-use crate::table_data::gen_table_row;
+use crate::records::BrokenRecord;
+use crate::standings::ClubStanding;
+use crate::table_data::{
+    MetricColumns, gen_appearance_row, gen_broken_record_row, gen_metrics_row, gen_points_header,
+    gen_points_rows, gen_standing_row, gen_start_list_row, gen_table_row, gen_team_row,
+};
 use hayro::{RenderSettings, render};
 use hayro_interpret::InterpreterSettings;
 use oxidize_pdf::text::table::GridStyle;
 use oxidize_pdf::{Color, Document, Font, HeaderStyle, Page, Table, TableOptions, TextAlign};
 use std::sync::Arc;
 
-pub fn gen_timesheet_pdf(event: RaceEvent) -> Result<Document, Box<dyn std::error::Error>> {
+pub fn gen_timesheet_pdf(
+    event: RaceEvent,
+    metric_columns: MetricColumns,
+) -> Result<Document, Box<dyn std::error::Error>> {
     let mut doc = Document::new();
-    doc.set_title(event.event.event_name);
+    doc.set_title(event.event.event_name.clone());
     let mut pages = vec![Page::a4()];
 
     let width = pages[0].width();
@@ -22,27 +33,51 @@ pub fn gen_timesheet_pdf(event: RaceEvent) -> Result<Document, Box<dyn std::erro
         .set_alignment(TextAlign::Center)
         .write_wrapped(&format!("{} - Results", event.event.event_code))?
         .write_paragraph(&format!("Start: {}", event.event.start_time))?;
+    if let Some(revision) = &event.revision {
+        flow.write_paragraph(&revision.marker())?;
+    }
     pages[0].add_text_flow(&flow);
 
     // Skaters Table
     // Splitting into two in order to not have a line above the table
-    let mut skater_table_header = Table::with_equal_columns(7, width * 0.95);
-    let mut skater_table = Table::with_equal_columns(7, width * 0.95);
+    // Team events list one row per team instead of one per skater
+    let headers = if event.teams.len() > 0 {
+        vec![
+            "Place".to_string(),
+            "Lane".to_string(),
+            "Team".to_string(),
+            "Members".to_string(),
+            "Time (PF)".to_string(),
+        ]
+    } else {
+        let mut headers = vec![
+            "Place".to_string(),
+            "ID".to_string(),
+            "Lane".to_string(),
+            "First Name".to_string(),
+            "Last Name".to_string(),
+            "Affiliation".to_string(),
+            "Time (PF)".to_string(),
+        ];
+        headers.extend(metric_columns.headers());
+        headers
+    };
+    let mut skater_table_header = Table::with_equal_columns(headers.len(), width * 0.95);
+    let mut skater_table = Table::with_equal_columns(headers.len(), width * 0.95);
 
-    for competitor in event.competitors.iter().rev() {
-        skater_table
-            .add_row_with_alignment(gen_table_row(competitor.clone()), TextAlign::Center)?;
+    if event.teams.len() > 0 {
+        for team in event.teams.iter().rev() {
+            skater_table.add_row_with_alignment(gen_team_row(team), TextAlign::Center)?;
+        }
+    } else {
+        for competitor in event.competitors.iter().rev() {
+            let mut row = gen_table_row(competitor.clone());
+            row.extend(gen_metrics_row(competitor, metric_columns));
+            skater_table.add_row_with_alignment(row, TextAlign::Center)?;
+        }
     }
 
-    skater_table_header.add_header_row(vec![
-        "Place".to_string(),
-        "ID".to_string(),
-        "Lane".to_string(),
-        "First Name".to_string(),
-        "Last Name".to_string(),
-        "Affiliation".to_string(),
-        "Time (PF)".to_string(),
-    ])?;
+    skater_table_header.add_header_row(headers)?;
 
     skater_table_header.set_position(
         (width * 0.05) / 2.0,
@@ -94,6 +129,70 @@ pub fn gen_timesheet_pdf(event: RaceEvent) -> Result<Document, Box<dyn std::erro
                     .font_size();
             }
         }
+
+        if is_lap_count_mismatch(&competitor.splits, event.event.expected_laps) {
+            flags_space_taken += pages[0]
+                .text()
+                .at(width * 0.05, flags_y - flags_space_taken)
+                .set_font(Font::HelveticaBold, 10.0)
+                .write_line(&format!(
+                    "Lap count mismatch, lane {}, place {}: {} splits, expected {}",
+                    competitor.lane.unwrap_or(255),
+                    competitor.place.unwrap_or(255),
+                    competitor.splits.len(),
+                    event.event.expected_laps.unwrap_or(0)
+                ))?
+                .font_size();
+        }
+    }
+
+    // Call out new records
+    for broken in &event.broken_records {
+        flags_space_taken += pages[0]
+            .text()
+            .at(width * 0.05, flags_y - flags_space_taken)
+            .set_font(Font::HelveticaBold, 10.0)
+            .write_line(&format!(
+                "New {} ({}): {} {} ({}) {}, previous {}",
+                broken.record.kind_name(),
+                broken.record.kind,
+                broken.first_name,
+                broken.last_name,
+                broken.club,
+                broken.time,
+                broken.record.time
+            ))?
+            .font_size();
+    }
+
+    // List who goes through to the next round
+    let qualified = event
+        .competitors
+        .iter()
+        .filter(|x| x.qualification.is_qualified())
+        .collect::<Vec<_>>();
+    if qualified.len() > 0 {
+        flags_space_taken += pages[0]
+            .text()
+            .at(width * 0.05, flags_y - flags_space_taken)
+            .set_font(Font::HelveticaBold, 10.0)
+            .write_line("Qualified (Q = place, q = time)")?
+            .font_size();
+        for competitor in qualified {
+            flags_space_taken += pages[0]
+                .text()
+                .at(width * 0.05, flags_y - flags_space_taken)
+                .set_font(Font::Helvetica, 10.0)
+                .write_line(&format!(
+                    "{}  {} {} ({}), place {}",
+                    competitor.qualification.mark(),
+                    competitor.first_name,
+                    competitor.last_name,
+                    competitor.club,
+                    competitor.place.unwrap_or(255)
+                ))?
+                .font_size();
+        }
     }
 
     let transponder_y = flags_y - flags_space_taken - 20.0;
@@ -193,6 +292,19 @@ pub fn gen_timesheet_pdf(event: RaceEvent) -> Result<Document, Box<dyn std::erro
 
     pages[0].add_table(&transponder_table)?;
 
+    if let Some(scoring) = &event.mass_start {
+        pages.push(gen_points_page(&event.event.event_code, scoring)?);
+    }
+
+    let categories = categories_in(&event);
+    if categories.len() > 0 {
+        gen_category_pages(&mut pages, &event, &categories)?;
+    }
+
+    if event.next_round.len() > 0 {
+        gen_next_round_pages(&mut pages, &event.event.event_code, &event.next_round)?;
+    }
+
     for page in pages {
         doc.add_page(page);
     }
@@ -201,27 +313,339 @@ pub fn gen_timesheet_pdf(event: RaceEvent) -> Result<Document, Box<dyn std::erro
     Ok(doc)
 }
 
-pub fn pdf_to_image(
-    document: &mut Document,
-) -> Result<(Vec<Vec<u8>>, u32, u32), Box<dyn std::error::Error>> {
-    let mut buffer = vec![];
-    document.write(&mut buffer)?;
+fn gen_points_page(
+    event_code: &str,
+    scoring: &MassStartScoring,
+) -> Result<Page, Box<dyn std::error::Error>> {
+    let mut page = Page::a4();
+    let width = page.width();
+    let height = page.height();
+
+    let mut flow = page.text_flow();
+    flow.at(0.0, height * 0.95)
+        .set_font(Font::HelveticaBold, 10.0)
+        .set_alignment(TextAlign::Center)
+        .write_wrapped(&format!("{} - Points", event_code))?;
+    page.add_text_flow(&flow);
+
+    let header = gen_points_header(scoring);
+    let mut table = Table::with_equal_columns(header.len(), width * 0.95);
+    table.add_header_row(header)?;
+    for row in gen_points_rows(scoring) {
+        table.add_row_with_alignment(row, TextAlign::Center)?;
+    }
+    table.set_options(TableOptions {
+        header_style: Some(HeaderStyle {
+            bold: true,
+            font: Font::HelveticaBold,
+            background_color: Color::white(),
+            text_color: Color::black(),
+        }),
+        ..Default::default()
+    });
+    table.set_position(
+        (width * 0.05) / 2.0,
+        flow.cursor_position().1 - table.get_height() - 20.0,
+    );
+    page.add_table(&table)?;
+
+    Ok(page)
+}
+
+fn gen_category_pages(
+    pages: &mut Vec<Page>,
+    event: &RaceEvent,
+    categories: &Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut page = Page::a4();
+    let width = page.width();
+    let height = page.height();
+
+    let mut flow = page.text_flow();
+    flow.at(0.0, height * 0.95)
+        .set_font(Font::HelveticaBold, 10.0)
+        .set_alignment(TextAlign::Center)
+        .write_wrapped(&format!("{} - Category Rankings", event.event.event_code))?;
+    page.add_text_flow(&flow);
+
+    let mut y = flow.cursor_position().1 - 20.0;
+    for category in categories {
+        let mut competitors = event
+            .competitors
+            .iter()
+            .filter(|x| x.category == *category)
+            .collect::<Vec<_>>();
+        competitors.sort_by_key(|x| x.category_place.unwrap_or(255));
+
+        let mut table = Table::with_equal_columns(6, width * 0.95);
+        table.add_header_row(vec![
+            "Place".to_string(),
+            "Overall".to_string(),
+            "ID".to_string(),
+            "Name".to_string(),
+            "Affiliation".to_string(),
+            "Time".to_string(),
+        ])?;
+        for competitor in competitors {
+            let row = gen_table_row(competitor.clone());
+            table.add_row_with_alignment(
+                vec![
+                    competitor
+                        .category_place
+                        .map(|x| x.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    row[0].clone(),
+                    row[1].clone(),
+                    format!("{} {}", competitor.first_name, competitor.last_name),
+                    competitor.club.clone(),
+                    row[6].clone(),
+                ],
+                TextAlign::Center,
+            )?;
+        }
+        table.set_options(TableOptions {
+            header_style: Some(HeaderStyle {
+                bold: true,
+                font: Font::HelveticaBold,
+                background_color: Color::white(),
+                text_color: Color::black(),
+            }),
+            ..Default::default()
+        });
+
+        // Start a fresh page if this category won't fit
+        if y - 20.0 - table.get_height() < height * 0.1 {
+            pages.push(page);
+            page = Page::a4();
+            y = height * 0.95;
+        }
+
+        page.text()
+            .at(width * 0.05, y)
+            .set_font(Font::HelveticaBold, 10.0)
+            .write_line(category)?;
+        table.set_position((width * 0.05) / 2.0, y - 10.0 - table.get_height());
+        page.add_table(&table)?;
+
+        y -= table.get_height() + 40.0;
+    }
+    pages.push(page);
+
+    Ok(())
+}
+
+fn gen_next_round_pages(
+    pages: &mut Vec<Page>,
+    event_code: &str,
+    heats: &Vec<Vec<CompetitorRow>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut page = Page::a4();
+    let width = page.width();
+    let height = page.height();
+
+    let mut flow = page.text_flow();
+    flow.at(0.0, height * 0.95)
+        .set_font(Font::HelveticaBold, 10.0)
+        .set_alignment(TextAlign::Center)
+        .write_wrapped(&format!("{} - Next Round Heats", event_code))?;
+    page.add_text_flow(&flow);
+
+    let mut y = flow.cursor_position().1 - 20.0;
+    for (i, heat) in heats.iter().enumerate() {
+        let mut table = Table::with_equal_columns(5, width * 0.95);
+        table.add_header_row(vec![
+            "Lane".to_string(),
+            "ID".to_string(),
+            "First Name".to_string(),
+            "Last Name".to_string(),
+            "Affiliation".to_string(),
+        ])?;
+        for competitor in heat {
+            table.add_row_with_alignment(gen_start_list_row(competitor), TextAlign::Center)?;
+        }
+        table.set_options(TableOptions {
+            header_style: Some(HeaderStyle {
+                bold: true,
+                font: Font::HelveticaBold,
+                background_color: Color::white(),
+                text_color: Color::black(),
+            }),
+            ..Default::default()
+        });
+
+        // Start a fresh page if this heat won't fit
+        if y - 20.0 - table.get_height() < height * 0.1 {
+            pages.push(page);
+            page = Page::a4();
+            y = height * 0.95;
+        }
+
+        page.text()
+            .at(width * 0.05, y)
+            .set_font(Font::HelveticaBold, 10.0)
+            .write_line(&format!("Heat {}", i + 1))?;
+        table.set_position((width * 0.05) / 2.0, y - 10.0 - table.get_height());
+        page.add_table(&table)?;
+
+        y -= table.get_height() + 40.0;
+    }
+    pages.push(page);
+
+    Ok(())
+}
+
+pub fn gen_records_report_pdf(
+    broken_records: &Vec<BrokenRecord>,
+) -> Result<Document, Box<dyn std::error::Error>> {
+    gen_table_report_pdf(
+        "Records Broken at this Meet",
+        vec![
+            "Record".to_string(),
+            "Distance".to_string(),
+            "Category".to_string(),
+            "Event".to_string(),
+            "Skater".to_string(),
+            "Affiliation".to_string(),
+            "Time".to_string(),
+            "Previous".to_string(),
+        ],
+        broken_records.iter().map(gen_broken_record_row).collect(),
+        "No records were broken.",
+    )
+}
+
+pub fn gen_club_standings_pdf(
+    standings: &Vec<ClubStanding>,
+) -> Result<Document, Box<dyn std::error::Error>> {
+    gen_table_report_pdf(
+        "Club Standings",
+        vec![
+            "Rank".to_string(),
+            "Club".to_string(),
+            "Points".to_string(),
+            "Races".to_string(),
+            "Wins".to_string(),
+        ],
+        standings.iter().map(gen_standing_row).collect(),
+        "No placings found.",
+    )
+}
+
+pub fn gen_skater_report_pdf(
+    query: &str,
+    appearances: &Vec<SkaterAppearance>,
+) -> Result<Document, Box<dyn std::error::Error>> {
+    gen_table_report_pdf(
+        &format!("Skater Report - {}", query),
+        vec![
+            "Event".to_string(),
+            "Start".to_string(),
+            "ID".to_string(),
+            "Name".to_string(),
+            "Affiliation".to_string(),
+            "Place".to_string(),
+            "Time".to_string(),
+            "Splits".to_string(),
+        ],
+        appearances.iter().map(gen_appearance_row).collect(),
+        "No races found.",
+    )
+}
+
+// A titled document holding a single table, carried over as many pages as it needs
+fn gen_table_report_pdf(
+    title: &str,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    empty_text: &str,
+) -> Result<Document, Box<dyn std::error::Error>> {
+    let mut doc = Document::new();
+    doc.set_title(title.to_string());
+
+    let mut pages = vec![];
+    let mut page = Page::a4();
+    let width = page.width();
+    let height = page.height();
 
-    let data = Arc::new(buffer);
+    let mut flow = page.text_flow();
+    flow.at(0.0, height * 0.95)
+        .set_font(Font::HelveticaBold, 10.0)
+        .set_alignment(TextAlign::Center)
+        .write_wrapped(title)?;
+    page.add_text_flow(&flow);
+
+    if rows.is_empty() {
+        page.text()
+            .at(width * 0.05, flow.cursor_position().1 - 20.0)
+            .set_font(Font::Helvetica, 10.0)
+            .write_line(empty_text)?;
+    }
+
+    // Fill each page with as many rows as fit, then carry on to the next
+    let mut y = flow.cursor_position().1 - 20.0;
+    let mut rows = rows.into_iter().peekable();
+    while rows.peek().is_some() {
+        let mut table = Table::with_equal_columns(headers.len(), width * 0.95);
+        table.add_header_row(headers.clone())?;
+        while let Some(row) = rows.peek() {
+            let mut counting_table = Table::with_equal_columns(headers.len(), width * 0.95);
+            counting_table.add_row(row.clone())?;
+            if y - table.get_height() - counting_table.get_height() < height * 0.1 {
+                break;
+            }
+            table.add_row_with_alignment(rows.next().unwrap(), TextAlign::Center)?;
+        }
+        table.set_options(TableOptions {
+            header_style: Some(HeaderStyle {
+                bold: true,
+                font: Font::HelveticaBold,
+                background_color: Color::white(),
+                text_color: Color::black(),
+            }),
+            ..Default::default()
+        });
+        table.set_position((width * 0.05) / 2.0, y - table.get_height());
+        page.add_table(&table)?;
+
+        if rows.peek().is_some() {
+            pages.push(page);
+            page = Page::a4();
+            y = height * 0.95;
+        }
+    }
+    pages.push(page);
+
+    for page in pages {
+        doc.add_page(page);
+    }
+
+    Ok(doc)
+}
+
+// Renders every page at `scale` pixels per point. `progress` is told how many of how many pages
+// are done before each page is rendered, and rendering stops with an error as soon as it returns
+// false.
+pub fn render_pdf(
+    data: Arc<Vec<u8>>,
+    scale: f32,
+    progress: impl Fn(usize, usize) -> bool,
+) -> Result<(Vec<Vec<u8>>, u32, u32), Box<dyn std::error::Error>> {
     let hayro_pdf = hayro::Pdf::new(data).expect("Internal movement of PDF data somehow failed");
 
     let interpreter_settings = InterpreterSettings::default();
 
-    let render_upscaling = 4.0;
-
     let render_settings = RenderSettings {
-        x_scale: render_upscaling,
-        y_scale: render_upscaling,
+        x_scale: scale,
+        y_scale: scale,
         ..Default::default()
     };
 
     let mut out = vec![];
-    for page in hayro_pdf.pages().iter() {
+    let page_count = hayro_pdf.pages().len();
+    for (i, page) in hayro_pdf.pages().iter().enumerate() {
+        if !progress(i, page_count) {
+            return Err("Rendering cancelled".into());
+        }
         let pixmap = render(page, &interpreter_settings, &render_settings);
         let vec1 = pixmap.take_u8();
         out.push(vec1);
@@ -230,7 +654,31 @@ pub fn pdf_to_image(
 
     Ok((
         out,
-        (render_upscaling * hayro_pdf.pages()[0].render_dimensions().0) as u32,
-        (render_upscaling * hayro_pdf.pages()[0].render_dimensions().1) as u32,
+        (scale * hayro_pdf.pages()[0].render_dimensions().0) as u32,
+        (scale * hayro_pdf.pages()[0].render_dimensions().1) as u32,
     ))
 }
+
+// Each page as a PNG, for printing through other programs
+#[cfg(target_os = "windows")]
+pub fn render_pdf_png(
+    data: Arc<Vec<u8>>,
+    scale: f32,
+) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    let hayro_pdf = hayro::Pdf::new(data).map_err(|_| "Failed to read PDF")?;
+
+    let interpreter_settings = InterpreterSettings::default();
+    let render_settings = RenderSettings {
+        x_scale: scale,
+        y_scale: scale,
+        ..Default::default()
+    };
+
+    let mut out = vec![];
+    for page in hayro_pdf.pages().iter() {
+        let pixmap = render(page, &interpreter_settings, &render_settings);
+        out.push(pixmap.take_png());
+    }
+
+    Ok(out)
+}
@@ -5,6 +5,11 @@ use fltk::enums::ColorDepth;
 use fltk::prelude::*;
 use fltk::window::Window;
 use fltk::{app, printer};
+use std::path::Path;
+use std::process::Command;
+
+// Pixels per point pages are drawn at for paper, about 290 dpi
+pub const PRINT_SCALE: f32 = 4.0;
 
 pub fn print_document(images: &Vec<Vec<u8>>, img_width: u32, img_height: u32) {
     let app = app::App::default();
@@ -51,3 +56,120 @@ pub fn print_document(images: &Vec<Vec<u8>>, img_width: u32, img_height: u32) {
 
     app.run().unwrap();
 }
+
+// Sends a saved PDF straight to the print spooler for unattended printing, with no dialog. A
+// blank printer name prints to the system default.
+#[cfg(not(target_os = "windows"))]
+pub fn print_pdf_file(path: &Path, printer: &str, copies: u32) -> Result<(), String> {
+    let mut command = Command::new("lp");
+    if !printer.trim().is_empty() {
+        command.arg("-d").arg(printer.trim());
+    }
+    let output = command
+        .arg("-n")
+        .arg(copies.max(1).to_string())
+        .arg("--")
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run lp, {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Printing {} failed, {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+// Windows has no spooler command for PDFs, so the pages are drawn here and sent through .NET as
+// a single job, with the copies left to the printer driver
+#[cfg(target_os = "windows")]
+pub fn print_pdf_file(path: &Path, printer: &str, copies: u32) -> Result<(), String> {
+    use crate::pdf::render_pdf_png;
+    use std::os::windows::process::CommandExt;
+    use std::sync::Arc;
+
+    // Keeps a console window from flashing up while PowerShell runs
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let quote = |x: &str| format!("'{}'", x.replace('\'', "''"));
+
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}, {}", path.display(), e))?;
+    let pages = render_pdf_png(Arc::new(data), PRINT_SCALE)
+        .map_err(|e| format!("Failed to render {} for printing, {}", path.display(), e))?;
+
+    let dir = std::env::temp_dir().join(format!("lifpdf_print_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}, {}", dir.display(), e))?;
+    let mut files = vec![];
+    for (i, page) in pages.iter().enumerate() {
+        let file = dir.join(format!("{}.png", i + 1));
+        std::fs::write(&file, page)
+            .map_err(|e| format!("Failed to write {}, {}", file.display(), e))?;
+        files.push(quote(&file.to_string_lossy()));
+    }
+
+    let mut script = String::from("Add-Type -AssemblyName System.Drawing\r\n");
+    script.push_str(&format!("$pages = @({})\r\n", files.join(", ")));
+    script.push_str("$doc = New-Object System.Drawing.Printing.PrintDocument\r\n");
+    script.push_str(&format!(
+        "$doc.DocumentName = {}\r\n",
+        quote(&path.file_name().unwrap_or_default().to_string_lossy())
+    ));
+    let missing = if printer.trim().is_empty() {
+        "No default printer set".to_string()
+    } else {
+        script.push_str(&format!(
+            "$doc.PrinterSettings.PrinterName = {}\r\n",
+            quote(printer.trim())
+        ));
+        format!("No printer called {}", printer.trim())
+    };
+    script.push_str(&format!(
+        "if (-not $doc.PrinterSettings.IsValid) {{ throw {} }}\r\n",
+        quote(&missing)
+    ));
+    script.push_str(&format!(
+        "$doc.PrinterSettings.Copies = {}\r\n",
+        copies.clamp(1, i16::MAX as u32)
+    ));
+    script.push_str(
+        "$doc.PrinterSettings.Collate = $true\r\n\
+         $doc.PrintController = New-Object System.Drawing.Printing.StandardPrintController\r\n\
+         $script:page = 0\r\n\
+         $doc.add_PrintPage({\r\n\
+         param($sender, $e)\r\n\
+         $image = [System.Drawing.Image]::FromFile($pages[$script:page])\r\n\
+         $e.Graphics.DrawImage($image, $e.PageBounds)\r\n\
+         $image.Dispose()\r\n\
+         $script:page++\r\n\
+         $e.HasMorePages = $script:page -lt $pages.Count\r\n\
+         })\r\n\
+         $doc.Print()\r\n",
+    );
+    let script_path = dir.join("print.ps1");
+    std::fs::write(&script_path, script)
+        .map_err(|e| format!("Failed to write {}, {}", script_path.display(), e))?;
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-ExecutionPolicy", "Bypass", "-File"])
+        .arg(&script_path)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+    let _ = std::fs::remove_dir_all(&dir);
+    let output = output.map_err(|e| format!("Failed to run powershell, {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Printing {} failed, {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
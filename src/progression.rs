@@ -0,0 +1,558 @@
+use crate::meet_db::load_race;
+use crate::parse::{CompetitorRow, EventRow, RaceEvent, is_lif_file_name};
+use std::cmp::Ordering;
+use std::fs::read_dir;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Qualification {
+    #[default]
+    None,
+    // Advanced on place within the heat
+    Place,
+    // Advanced as one of the fastest skaters who didn't place through
+    Time,
+}
+
+impl Qualification {
+    pub fn mark(&self) -> &'static str {
+        match self {
+            Qualification::None => "",
+            Qualification::Place => "Q",
+            Qualification::Time => "q",
+        }
+    }
+
+    pub fn from_mark(mark: &str) -> Self {
+        match mark {
+            "Q" => Qualification::Place,
+            "q" => Qualification::Time,
+            _ => Qualification::None,
+        }
+    }
+
+    pub fn is_qualified(&self) -> bool {
+        *self != Qualification::None
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AdvancementRule {
+    pub top_per_heat: u8,
+    pub best_time_qualifiers: u8,
+}
+
+#[derive(Clone, Debug)]
+pub enum Seeding {
+    // Place qualifiers by place, then time qualifiers by time
+    Place,
+    // Every qualifier by time, regardless of how they qualified
+    Time,
+    // One list per heat, giving the seed for each lane in order
+    DrawTable(Vec<Vec<usize>>),
+}
+
+impl Seeding {
+    // Matches the order of the seeding combo box in settings
+    pub fn from_index(index: i32, draw_table: &str) -> Result<Self, String> {
+        match index {
+            1 => Ok(Seeding::Time),
+            2 => Ok(Seeding::DrawTable(parse_draw_table(draw_table)?)),
+            _ => Ok(Seeding::Place),
+        }
+    }
+}
+
+// Each non-empty line is a heat, with comma separated seed numbers in lane order, e.g.
+// "1,4,5,8" on the first line and "2,3,6,7" on the second. Every seed from 1 up to the highest
+// has to appear exactly once.
+pub fn parse_draw_table(raw: &str) -> Result<Vec<Vec<usize>>, String> {
+    let mut table = vec![];
+    for line in raw.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut heat = vec![];
+        for seed in line.split(",") {
+            let seed = seed.trim();
+            heat.push(match seed.parse::<usize>() {
+                Ok(x) if x > 0 => x,
+                _ => return Err(format!("Invalid seed \"{}\" in draw table", seed)),
+            });
+        }
+        table.push(heat);
+    }
+
+    if table.is_empty() {
+        return Err("Draw table is empty".to_string());
+    }
+
+    let mut seeds = table.iter().flatten().copied().collect::<Vec<_>>();
+    seeds.sort();
+    for (i, seed) in seeds.iter().enumerate() {
+        if i > 0 && seeds[i - 1] == *seed {
+            return Err(format!("Seed {} appears more than once in draw table", seed));
+        }
+    }
+    if let Some(missing) = (1..=seeds.len()).find(|x| seeds.binary_search(x).is_err()) {
+        return Err(format!("Seed {} is missing from draw table", missing));
+    }
+
+    Ok(table)
+}
+
+fn has_result(competitor: &CompetitorRow) -> bool {
+    competitor.place.unwrap_or(255) != 255
+        && competitor.time.map(|t| !t.is_no_time()).unwrap_or(false)
+}
+
+fn cmp_time(first: &CompetitorRow, other: &CompetitorRow) -> Ordering {
+    let first = first.time.map(|t| t.total_secs()).unwrap_or(f32::MAX);
+    let other = other.time.map(|t| t.total_secs()).unwrap_or(f32::MAX);
+    first.total_cmp(&other)
+}
+
+// Marks every competitor across the heats of a round as qualified on place (Q), on time (q),
+// or not qualified.
+pub fn apply_advancement(heats: &mut [RaceEvent], rule: AdvancementRule) {
+    let mut time_candidates = vec![];
+    for (h, heat) in heats.iter_mut().enumerate() {
+        for (c, competitor) in heat.competitors.iter_mut().enumerate() {
+            competitor.qualification = Qualification::None;
+            if !has_result(competitor) {
+                continue;
+            }
+
+            if competitor.place.unwrap() <= rule.top_per_heat {
+                competitor.qualification = Qualification::Place;
+            } else {
+                time_candidates.push((h, c));
+            }
+        }
+    }
+
+    time_candidates.sort_by(|x, y| {
+        cmp_time(&heats[x.0].competitors[x.1], &heats[y.0].competitors[y.1])
+    });
+    for (h, c) in time_candidates
+        .into_iter()
+        .take(rule.best_time_qualifiers as usize)
+    {
+        heats[h].competitors[c].qualification = Qualification::Time;
+    }
+}
+
+// All qualified competitors of a round, ranked with place qualifiers first (by place, then time)
+// followed by time qualifiers (by time).
+pub fn qualifiers(heats: &[RaceEvent]) -> Vec<CompetitorRow> {
+    let mut out = heats
+        .iter()
+        .flat_map(|x| x.competitors.iter())
+        .filter(|x| x.qualification.is_qualified())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    out.sort_by(|x, y| {
+        let kind = |c: &CompetitorRow| match c.qualification {
+            Qualification::Place => 0,
+            _ => 1,
+        };
+        kind(x).cmp(&kind(y)).then_with(|| {
+            if x.qualification == Qualification::Place && y.qualification == Qualification::Place {
+                x.place.cmp(&y.place).then_with(|| cmp_time(x, y))
+            } else {
+                cmp_time(x, y)
+            }
+        })
+    });
+
+    out
+}
+
+// Splits the ranked qualifiers into heats of at most `heat_size` skaters. With place or time
+// seeding, skaters snake across the heats so that every heat gets a similar spread, and lanes
+// follow the seed. A draw table places each seed directly.
+pub fn next_round_heats(
+    qualified: &[CompetitorRow],
+    seeding: &Seeding,
+    heat_size: usize,
+) -> Vec<Vec<CompetitorRow>> {
+    if qualified.is_empty() {
+        return vec![];
+    }
+
+    let mut heats = match seeding {
+        Seeding::DrawTable(table) => {
+            let mut heats = vec![];
+            for seeds in table {
+                let mut heat = vec![];
+                for (lane, seed) in seeds.iter().enumerate() {
+                    if let Some(competitor) = qualified.get(seed - 1) {
+                        let mut competitor = competitor.clone();
+                        competitor.lane = Some(lane as u8 + 1);
+                        heat.push(competitor);
+                    }
+                }
+                if heat.len() > 0 {
+                    heats.push(heat);
+                }
+            }
+            heats
+        }
+        _ => {
+            if heat_size == 0 {
+                return vec![];
+            }
+
+            let mut ranked = qualified.to_vec();
+            if let Seeding::Time = seeding {
+                ranked.sort_by(cmp_time);
+            }
+
+            let heat_count = ranked.len().div_ceil(heat_size);
+            let mut heats = vec![vec![]; heat_count];
+            for (i, competitor) in ranked.into_iter().enumerate() {
+                let pass = i / heat_count;
+                let idx = i % heat_count;
+                let heat = if pass % 2 == 0 {
+                    idx
+                } else {
+                    heat_count - 1 - idx
+                };
+                heats[heat].push(competitor);
+            }
+
+            for heat in heats.iter_mut() {
+                for (i, competitor) in heat.iter_mut().enumerate() {
+                    competitor.lane = Some(i as u8 + 1);
+                }
+            }
+            heats
+        }
+    };
+
+    for heat in heats.iter_mut() {
+        for competitor in heat.iter_mut() {
+            competitor.place = None;
+            competitor.time = None;
+            competitor.splits = vec![];
+            competitor.qualification = Qualification::None;
+        }
+    }
+
+    heats
+}
+
+// Parses every heat of the same event and round found in `dir`, ordered by heat number.
+pub fn load_round(dir: &Path, event: &EventRow) -> Vec<RaceEvent> {
+    let mut heats = vec![];
+    for file in match read_dir(dir) {
+        Ok(x) => x.collect(),
+        Err(_) => vec![],
+    } {
+        if let Ok(file) = file {
+            if !is_lif_file_name(&file.file_name().to_string_lossy()) {
+                continue;
+            }
+
+            if let Ok(heat) = load_race(&file.path()) {
+                if heat.event.event_code == event.event_code && heat.event.round == event.round {
+                    heats.push(heat);
+                }
+            }
+        }
+    }
+    heats.sort_by(|x, y| x.event.heat.cmp(&y.event.heat));
+
+    heats
+}
+
+// Works out qualification for the heat at `path` against the other heats of its round, and
+// seeds the next round from every qualifier found so far. Returns how many heats of the round
+// were found.
+pub fn progress_event(
+    event: &mut RaceEvent,
+    path: &Path,
+    rule: AdvancementRule,
+    seeding: &Seeding,
+    next_heat_size: usize,
+) -> usize {
+    let mut heats = match path.parent() {
+        Some(dir) => load_round(dir, &event.event),
+        None => vec![],
+    };
+
+    // The selected heat always takes part, even if it was filtered out on disk
+    let idx = match heats.iter().position(|x| x.event.heat == event.event.heat) {
+        Some(i) => {
+            heats[i] = event.clone();
+            i
+        }
+        None => {
+            heats.push(event.clone());
+            heats.len() - 1
+        }
+    };
+
+    apply_advancement(&mut heats, rule);
+
+    event.competitors = heats[idx].competitors.clone();
+    event.next_round = next_round_heats(&qualifiers(&heats), seeding, next_heat_size);
+
+    heats.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::SkaterTime;
+
+    // Qualifiers ranked 1 to `count`, with the slowest times for the best ranked
+    fn ranked(count: u32) -> Vec<CompetitorRow> {
+        (1..=count)
+            .map(|x| CompetitorRow {
+                place: Some(1),
+                skater_id: Some(x),
+                lane: Some(9),
+                time: Some(SkaterTime::from_secs(100.0 - x as f32)),
+                qualification: Qualification::Place,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // Finishers as (skater id, place, time), with place 255 for DNF or DQ and no time for DNF
+    fn heat(number: u8, finishers: &[(u32, u8, Option<f32>)]) -> RaceEvent {
+        let no_time = SkaterTime {
+            subsecond: -1.0,
+            ..Default::default()
+        };
+        RaceEvent {
+            event: EventRow {
+                event_code: "1".to_string(),
+                round: Some(1),
+                heat: Some(number),
+                ..Default::default()
+            },
+            competitors: finishers
+                .iter()
+                .map(|x| CompetitorRow {
+                    skater_id: Some(x.0),
+                    place: Some(x.1),
+                    time: Some(x.2.map(SkaterTime::from_secs).unwrap_or(no_time)),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn marks(heats: &[RaceEvent]) -> Vec<Vec<(u32, &'static str)>> {
+        heats
+            .iter()
+            .map(|x| {
+                x.competitors
+                    .iter()
+                    .map(|x| (x.skater_id.unwrap(), x.qualification.mark()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn ids(heats: &[Vec<CompetitorRow>]) -> Vec<Vec<u32>> {
+        heats
+            .iter()
+            .map(|x| x.iter().map(|x| x.skater_id.unwrap()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn advancement_by_place_then_time() {
+        let mut heats = vec![
+            heat(
+                1,
+                &[(1, 1, Some(44.0)), (2, 2, Some(44.5)), (3, 3, Some(46.0))],
+            ),
+            heat(
+                2,
+                &[(4, 1, Some(45.0)), (5, 2, Some(45.2)), (6, 3, Some(45.8))],
+            ),
+        ];
+        apply_advancement(
+            &mut heats,
+            AdvancementRule {
+                top_per_heat: 1,
+                best_time_qualifiers: 2,
+            },
+        );
+
+        assert_eq!(
+            marks(&heats),
+            vec![
+                vec![(1, "Q"), (2, "q"), (3, "")],
+                vec![(4, "Q"), (5, "q"), (6, "")]
+            ]
+        );
+        let order = qualifiers(&heats)
+            .iter()
+            .map(|x| x.skater_id.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![1, 4, 2, 5]);
+    }
+
+    #[test]
+    fn advancement_skips_dnf_and_dq() {
+        let mut heats = vec![
+            heat(1, &[(1, 255, None), (2, 1, Some(45.0)), (3, 2, Some(47.0))]),
+            heat(2, &[(4, 255, Some(43.0)), (5, 1, Some(46.0)), (6, 2, None)]),
+        ];
+        apply_advancement(
+            &mut heats,
+            AdvancementRule {
+                top_per_heat: 1,
+                best_time_qualifiers: 3,
+            },
+        );
+
+        // DQ with a time, DNF, and a place without a time all miss out, even with spots left
+        assert_eq!(
+            marks(&heats),
+            vec![
+                vec![(1, ""), (2, "Q"), (3, "q")],
+                vec![(4, ""), (5, "Q"), (6, "")]
+            ]
+        );
+        assert_eq!(qualifiers(&heats).len(), 3);
+    }
+
+    #[test]
+    fn advancement_with_ties() {
+        let mut heats = vec![
+            heat(
+                1,
+                &[(1, 1, Some(45.0)), (2, 1, Some(45.0)), (3, 3, Some(46.0))],
+            ),
+            heat(
+                2,
+                &[(4, 1, Some(44.0)), (5, 2, Some(46.0)), (6, 3, Some(47.0))],
+            ),
+        ];
+        apply_advancement(
+            &mut heats,
+            AdvancementRule {
+                top_per_heat: 1,
+                best_time_qualifiers: 1,
+            },
+        );
+
+        // A dead heat for a qualifying place takes both skaters through, and a tie on time
+        // goes to the earlier heat
+        assert_eq!(
+            marks(&heats),
+            vec![
+                vec![(1, "Q"), (2, "Q"), (3, "q")],
+                vec![(4, "Q"), (5, ""), (6, "")]
+            ]
+        );
+        let order = qualifiers(&heats)
+            .iter()
+            .map(|x| x.skater_id.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn advancement_clears_earlier_marks() {
+        let mut heats = vec![heat(1, &[(1, 1, Some(45.0)), (2, 2, Some(46.0))])];
+        heats[0].competitors[1].qualification = Qualification::Time;
+        apply_advancement(
+            &mut heats,
+            AdvancementRule {
+                top_per_heat: 1,
+                best_time_qualifiers: 0,
+            },
+        );
+
+        assert_eq!(marks(&heats), vec![vec![(1, "Q"), (2, "")]]);
+    }
+
+    #[test]
+    fn snake_seeding_fills_even_heats() {
+        let heats = next_round_heats(&ranked(8), &Seeding::Place, 4);
+        assert_eq!(ids(&heats), vec![vec![1, 4, 5, 8], vec![2, 3, 6, 7]]);
+    }
+
+    #[test]
+    fn snake_seeding_fills_uneven_heats() {
+        let heats = next_round_heats(&ranked(9), &Seeding::Place, 4);
+        assert_eq!(
+            ids(&heats),
+            vec![vec![1, 6, 7], vec![2, 5, 8], vec![3, 4, 9]]
+        );
+
+        let heats = next_round_heats(&ranked(5), &Seeding::Place, 4);
+        assert_eq!(ids(&heats), vec![vec![1, 4, 5], vec![2, 3]]);
+    }
+
+    #[test]
+    fn single_heat_keeps_rank_order() {
+        let heats = next_round_heats(&ranked(4), &Seeding::Place, 4);
+        assert_eq!(ids(&heats), vec![vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn time_seeding_ranks_by_time() {
+        let heats = next_round_heats(&ranked(4), &Seeding::Time, 2);
+        assert_eq!(ids(&heats), vec![vec![4, 1], vec![3, 2]]);
+    }
+
+    #[test]
+    fn next_round_starts_without_results() {
+        let heats = next_round_heats(&ranked(6), &Seeding::Place, 3);
+        for heat in &heats {
+            let lanes = heat.iter().map(|x| x.lane).collect::<Vec<_>>();
+            assert_eq!(lanes, vec![Some(1), Some(2), Some(3)]);
+            for competitor in heat {
+                assert_eq!(competitor.place, None);
+                assert!(competitor.time.is_none());
+                assert_eq!(competitor.qualification, Qualification::None);
+            }
+        }
+    }
+
+    #[test]
+    fn no_heats_without_qualifiers_or_heat_size() {
+        assert!(next_round_heats(&[], &Seeding::Place, 4).is_empty());
+        assert!(next_round_heats(&ranked(4), &Seeding::Place, 0).is_empty());
+    }
+
+    #[test]
+    fn draw_table_places_seeds_by_lane() {
+        let table = parse_draw_table("3,1\n2,4,5\n").unwrap();
+        let heats = next_round_heats(&ranked(4), &Seeding::DrawTable(table), 0);
+        assert_eq!(ids(&heats), vec![vec![3, 1], vec![2, 4]]);
+
+        let lanes = heats[1].iter().map(|x| x.lane).collect::<Vec<_>>();
+        assert_eq!(lanes, vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn draw_table_is_validated() {
+        assert_eq!(
+            parse_draw_table("1, 4\n\n2,3").unwrap(),
+            vec![vec![1, 4], vec![2, 3]]
+        );
+        assert!(parse_draw_table("").is_err());
+        assert!(parse_draw_table("1,x").is_err());
+        assert!(parse_draw_table("0,1").is_err());
+        assert_eq!(
+            parse_draw_table("1,2\n2,3"),
+            Err("Seed 2 appears more than once in draw table".to_string())
+        );
+        assert_eq!(
+            parse_draw_table("1,2\n4"),
+            Err("Seed 3 is missing from draw table".to_string())
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use crate::parse::{CompetitorRow, RaceEvent, SkaterTime, parse_time, split_csv_line};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// The last records file read, with when it was modified, so it's only read again when the setting
+// points somewhere else or the file is replaced rather than for every race
+static IMPORTED: Mutex<Option<(String, Option<SystemTime>, Vec<Record>)>> = Mutex::new(None);
+
+#[derive(Clone, Debug, Default)]
+pub struct Record {
+    // Short code printed next to times, e.g. TR, PR or NR
+    pub kind: String,
+    pub distance_m: f32,
+    pub time: SkaterTime,
+    // Blank for records open to every category or gender
+    pub category: String,
+    pub gender: String,
+    pub holder: String,
+    pub date: String,
+}
+
+impl Record {
+    pub fn kind_name(&self) -> String {
+        match self.kind.as_str() {
+            "TR" => "Track Record".to_string(),
+            "PR" => "Provincial Record".to_string(),
+            "NR" => "National Record".to_string(),
+            "WR" => "World Record".to_string(),
+            _ => format!("{} Record", self.kind),
+        }
+    }
+
+    pub fn applies_to(&self, category: Option<&str>, gender: Option<&str>) -> bool {
+        let matches = |field: &str, value: Option<&str>| {
+            field.is_empty()
+                || value
+                    .map(|x| x.trim().eq_ignore_ascii_case(field))
+                    .unwrap_or(false)
+        };
+
+        matches(&self.category, category) && matches(&self.gender, gender)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BrokenRecord {
+    pub record: Record,
+    pub event_code: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub club: String,
+    pub time: SkaterTime,
+}
+
+// The first line names the columns. "type", "distance" and "time" are required, and "category",
+// "gender", "holder" and "date" are optional.
+pub fn parse_records_csv(raw: &str) -> Result<Vec<Record>, String> {
+    let mut lines = raw.lines().filter(|x| !x.trim().is_empty());
+    let header = split_csv_line(lines.next().ok_or("Records file is empty")?)
+        .iter()
+        .map(|x| x.to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |name: &str| header.iter().position(|x| x == name);
+
+    let kind_idx = column("type").ok_or("Records file has no \"type\" column")?;
+    let distance_idx = column("distance").ok_or("Records file has no \"distance\" column")?;
+    let time_idx = column("time").ok_or("Records file has no \"time\" column")?;
+
+    let mut out = vec![];
+    for line in lines {
+        let fields = split_csv_line(line);
+        let field = |idx: Option<usize>| {
+            idx.and_then(|i| fields.get(i))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let distance = field(Some(distance_idx));
+        let time = field(Some(time_idx));
+        out.push(Record {
+            kind: field(Some(kind_idx)).to_uppercase(),
+            distance_m: distance
+                .trim_end_matches(|x: char| x.is_alphabetic() || x.is_whitespace())
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid record distance \"{}\"", distance))?,
+            time: parse_time(time.clone())
+                .ok()
+                .filter(|x| !x.is_no_time())
+                .ok_or(format!("Invalid record time \"{}\"", time))?,
+            category: field(column("category")),
+            gender: field(column("gender")),
+            holder: field(column("holder")),
+            date: field(column("date")),
+        });
+    }
+
+    Ok(out)
+}
+
+// The records as imported, before anything at the meet
+pub fn load_records(path: &str) -> Result<Vec<Record>, String> {
+    if path.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let modified = std::fs::metadata(path).and_then(|x| x.modified()).ok();
+    let mut imported = IMPORTED.lock().unwrap();
+    if let Some((cached_path, cached_modified, records)) = imported.as_ref() {
+        if cached_path == path && *cached_modified == modified {
+            return Ok(records.clone());
+        }
+    }
+
+    let raw =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}, {}", path, e))?;
+    let records = parse_records_csv(&raw)?;
+    *imported = Some((path.to_string(), modified, records.clone()));
+
+    Ok(records)
+}
+
+fn breaks(record: &Record, competitor: &CompetitorRow, distance_m: f32) -> bool {
+    let time = match competitor.time {
+        Some(t) if !t.is_no_time() => t,
+        _ => return false,
+    };
+
+    record.distance_m == distance_m
+        && record.applies_to(
+            Some(&competitor.category).filter(|x| !x.is_empty()).map(|x| x.as_str()),
+            Some(&competitor.gender).filter(|x| !x.is_empty()).map(|x| x.as_str()),
+        )
+        && time.total_secs() < record.time.total_secs()
+}
+
+// Marks the fastest competitor under each record at the race distance and lists the broken
+// records on the event for the timesheet callout. A broken record takes on the new time, so
+// races marked in the order they were skated are each measured against the best before them.
+pub fn mark_records(event: &mut RaceEvent, records: &mut Vec<Record>) {
+    event.broken_records = vec![];
+    for competitor in event.competitors.iter_mut() {
+        competitor.records_broken = vec![];
+    }
+    let distance_m = match event.event.distance_m {
+        Some(x) => x,
+        None => return,
+    };
+
+    for record in records.iter_mut() {
+        let fastest = event
+            .competitors
+            .iter()
+            .enumerate()
+            .filter(|x| breaks(record, x.1, distance_m))
+            .min_by(|x, y| {
+                let x = x.1.time.unwrap().total_secs();
+                let y = y.1.time.unwrap().total_secs();
+                x.total_cmp(&y)
+            })
+            .map(|x| x.0);
+        let competitor = match fastest {
+            Some(i) => &mut event.competitors[i],
+            None => continue,
+        };
+
+        competitor.records_broken.push(record.kind.clone());
+        event.broken_records.push(BrokenRecord {
+            record: record.clone(),
+            event_code: event.event.event_code.clone(),
+            first_name: competitor.first_name.clone(),
+            last_name: competitor.last_name.clone(),
+            club: competitor.club.clone(),
+            time: competitor.time.unwrap(),
+        });
+
+        record.time = competitor.time.unwrap();
+        record.holder = format!("{} {}", competitor.first_name, competitor.last_name)
+            .trim()
+            .to_string();
+    }
+
+    for competitor in event.competitors.iter_mut() {
+        competitor.records_broken.sort();
+        competitor.records_broken.dedup();
+    }
+}
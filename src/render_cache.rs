@@ -0,0 +1,86 @@
+use crate::parse::RaceEvent;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+
+// Renders are large, a few pages at full resolution each, so only the latest few are kept
+const CACHE_SIZE: usize = 8;
+
+pub struct RenderedRace {
+    pub key: RenderKey,
+    // Prepared, as shown in the preview tables
+    pub event: RaceEvent,
+    pub pdf: Arc<Vec<u8>>,
+    pub images: Vec<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+    // Pixels per point the pages were rendered at
+    pub scale: f32,
+}
+
+// A render stays valid while the file and the settings it was made with are unchanged. What
+// other races add, like PBs, records and qualifiers, is dropped from the cache by the watcher
+// when any race changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderKey {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+    settings: u64,
+}
+
+impl RenderKey {
+    pub fn new(path: &Path, settings: u64) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            modified: metadata.modified().ok()?,
+            size: metadata.len(),
+            settings,
+        })
+    }
+}
+
+// Most recently used last
+#[derive(Default)]
+pub struct RenderCache {
+    entries: Vec<Arc<RenderedRace>>,
+}
+
+pub static RENDER_CACHE: LazyLock<Mutex<RenderCache>> =
+    LazyLock::new(|| Mutex::new(RenderCache::default()));
+
+impl RenderCache {
+    pub fn get(&mut self, key: &RenderKey) -> Option<Arc<RenderedRace>> {
+        let i = self.entries.iter().position(|x| &x.key == key)?;
+        let entry = self.entries.remove(i);
+        self.entries.push(entry.clone());
+
+        Some(entry)
+    }
+
+    // Older renders of the same race can't be hit again, so they go at the same time
+    pub fn insert(&mut self, render: Arc<RenderedRace>) {
+        self.entries.retain(|x| x.key.path != render.key.path);
+        self.entries.push(render);
+        if self.entries.len() > CACHE_SIZE {
+            self.entries.remove(0);
+        }
+    }
+
+    // Swaps in pages rendered again at another zoom level, if the race is still cached
+    pub fn replace(&mut self, render: Arc<RenderedRace>) {
+        if let Some(entry) = self.entries.iter_mut().find(|x| x.key == render.key) {
+            *entry = render;
+        }
+    }
+
+    // For changes the key can't see, like sprint placings saved beside the race
+    pub fn invalidate(&mut self, path: &Path) {
+        self.entries.retain(|x| x.key.path != path);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,209 @@
+use crate::history::civil_date;
+use crate::parse::CompetitorRow;
+
+#[derive(Clone, Debug, Default)]
+pub struct Revision {
+    // The first published version of a race is revision 1, so stored revisions start at 2
+    pub number: u32,
+    // Seconds since the epoch the changed file was read
+    pub changed_at: u64,
+    pub changes: Vec<String>,
+}
+
+impl Revision {
+    pub fn marker(&self) -> String {
+        format!(
+            "Revision {} - {}",
+            self.number,
+            format_timestamp(self.changed_at)
+        )
+    }
+}
+
+// There's no time zone data to go on, so times are given in UTC and say so
+pub fn format_timestamp(unix_secs: u64) -> String {
+    let (year, month, day) = civil_date(unix_secs);
+    let secs_of_day = unix_secs % 86400;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60
+    )
+}
+
+fn name_of(competitor: &CompetitorRow) -> String {
+    format!("{} {}", competitor.first_name, competitor.last_name)
+        .trim()
+        .to_string()
+}
+
+fn place_of(competitor: &CompetitorRow) -> String {
+    match competitor.place {
+        Some(255) => "DNF".to_string(),
+        Some(x) => x.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn time_of(competitor: &CompetitorRow) -> String {
+    match competitor.time {
+        Some(t) if !t.is_no_time() => t.to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+// Skaters are matched by ID where the file has one, and by lane otherwise
+fn same_skater(first: &CompetitorRow, other: &CompetitorRow) -> bool {
+    match (first.skater_id, other.skater_id) {
+        (Some(x), Some(y)) if x != i32::MAX as u32 && y != i32::MAX as u32 => x == y,
+        _ => first.lane.is_some() && first.lane == other.lane,
+    }
+}
+
+// One line per difference between two versions of a race's results
+pub fn diff_competitors(old: &Vec<CompetitorRow>, new: &Vec<CompetitorRow>) -> Vec<String> {
+    let mut out = vec![];
+    for competitor in new {
+        let before = match old.iter().find(|x| same_skater(x, competitor)) {
+            Some(x) => x,
+            None => {
+                out.push(format!("Added {}", name_of(competitor)));
+                continue;
+            }
+        };
+
+        let name = name_of(competitor);
+        if name_of(before) != name {
+            out.push(format!("Name {} changed to {}", name_of(before), name));
+        }
+        if before.club != competitor.club {
+            out.push(format!(
+                "{}: club {} changed to {}",
+                name, before.club, competitor.club
+            ));
+        }
+        if place_of(before) != place_of(competitor) {
+            out.push(format!(
+                "{}: place {} changed to {}",
+                name,
+                place_of(before),
+                place_of(competitor)
+            ));
+        }
+        if time_of(before) != time_of(competitor) {
+            out.push(format!(
+                "{}: time {} changed to {}",
+                name,
+                time_of(before),
+                time_of(competitor)
+            ));
+        }
+    }
+
+    for competitor in old {
+        if !new.iter().any(|x| same_skater(competitor, x)) {
+            out.push(format!("Removed {}", name_of(competitor)));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::SkaterTime;
+
+    fn skater(id: Option<u32>, lane: u8, name: &str, place: u8, secs: f32) -> CompetitorRow {
+        CompetitorRow {
+            skater_id: id,
+            lane: Some(lane),
+            first_name: name.to_string(),
+            last_name: "Smith".to_string(),
+            club: "Eagles".to_string(),
+            place: Some(place),
+            time: Some(SkaterTime::from_secs(secs)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unchanged_results_have_no_differences() {
+        let results = vec![skater(Some(1), 1, "Sam", 1, 45.5)];
+        assert!(diff_competitors(&results, &results.clone()).is_empty());
+    }
+
+    #[test]
+    fn changed_fields_are_listed() {
+        let old = vec![
+            skater(Some(1), 1, "Sam", 1, 45.5),
+            skater(Some(2), 2, "Alex", 2, 46.25),
+        ];
+        let mut new = old.clone();
+        new[0].time = Some(SkaterTime::from_secs(45.25));
+        new[1].first_name = "Alexis".to_string();
+        new[1].club = "Hawks".to_string();
+        new[1].place = Some(255);
+
+        assert_eq!(
+            diff_competitors(&old, &new),
+            vec![
+                "Sam Smith: time 45.500 changed to 45.250",
+                "Name Alex Smith changed to Alexis Smith",
+                "Alexis Smith: club Eagles changed to Hawks",
+                "Alexis Smith: place 2 changed to DNF",
+            ]
+        );
+    }
+
+    #[test]
+    fn skaters_are_matched_by_id_before_lane() {
+        // Swapped lanes, but the same skaters
+        let old = vec![
+            skater(Some(1), 1, "Sam", 1, 45.5),
+            skater(Some(2), 2, "Alex", 2, 46.25),
+        ];
+        let new = vec![
+            skater(Some(2), 1, "Alex", 2, 46.25),
+            skater(Some(1), 2, "Sam", 1, 45.5),
+        ];
+        assert!(diff_competitors(&old, &new).is_empty());
+
+        // Without IDs the lane decides
+        let old = vec![skater(None, 1, "Sam", 1, 45.5)];
+        let new = vec![skater(Some(i32::MAX as u32), 1, "Sam", 2, 45.5)];
+        assert_eq!(
+            diff_competitors(&old, &new),
+            vec!["Sam Smith: place 1 changed to 2"]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_skaters_are_listed() {
+        let old = vec![
+            skater(Some(1), 1, "Sam", 1, 45.5),
+            skater(Some(2), 2, "Alex", 2, 46.25),
+        ];
+        let new = vec![
+            skater(Some(1), 1, "Sam", 1, 45.5),
+            skater(Some(3), 3, "Jo", 2, 47.0),
+        ];
+        assert_eq!(
+            diff_competitors(&old, &new),
+            vec!["Added Jo Smith", "Removed Alex Smith"]
+        );
+    }
+
+    #[test]
+    fn revision_marker_is_in_utc() {
+        let revision = Revision {
+            number: 2,
+            changed_at: 1709164800 + 13 * 3600 + 5 * 60 + 59,
+            changes: vec![],
+        };
+        assert_eq!(revision.marker(), "Revision 2 - 2024-02-29 13:05 UTC");
+    }
+}
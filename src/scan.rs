@@ -0,0 +1,251 @@
+use crate::SettingsData;
+use slint::Model;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+// Each search path has its own patterns, separated by commas or new lines. One ending in "/"
+// matches a folder anywhere below the search path, one containing "/" matches the path relative
+// to the search path, and any other matches the file name alone. Case is ignored, as the timing
+// software and Windows don't keep it consistent. No include patterns means only LIF files.
+pub struct FileFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+fn split_patterns(raw: &str) -> Vec<String> {
+    raw.split(|x| x == ',' || x == '\n')
+        .map(|x| x.trim().replace('\\', "/"))
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+// "*" matches within one folder, "**" across folders and "?" any single character
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            (0..=text.len()).any(|i| glob_match(&pattern[2..], &text[i..]))
+        }
+        Some('*') => {
+            for i in 0..=text.len() {
+                if glob_match(&pattern[1..], &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => !text.is_empty() && text[0] != '/' && glob_match(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+fn pattern_matches(pattern: &str, relative: &str) -> bool {
+    let glob = |pattern: &str, text: &str| {
+        glob_match(
+            &pattern.to_lowercase().chars().collect::<Vec<_>>(),
+            &text.to_lowercase().chars().collect::<Vec<_>>(),
+        )
+    };
+
+    let mut components = relative.split('/').collect::<Vec<_>>();
+    let name = components.pop().unwrap_or_default();
+    if let Some(folder) = pattern.strip_suffix('/') {
+        components.iter().any(|x| glob(folder, x))
+    } else if pattern.contains('/') {
+        glob(pattern.trim_start_matches('/'), relative)
+    } else {
+        glob(pattern, name)
+    }
+}
+
+impl FileFilter {
+    pub fn new(include: &str, exclude: &str) -> Self {
+        let mut include = split_patterns(include);
+        if include.is_empty() {
+            include.push("*.lif".to_string());
+        }
+
+        Self {
+            include,
+            exclude: split_patterns(exclude),
+        }
+    }
+
+    // The filter of the search path at `index`
+    pub fn from_settings(settings: &SettingsData, index: usize) -> Self {
+        Self::new(
+            &settings.include_globs.row_data(index).unwrap_or_default(),
+            &settings.exclude_globs.row_data(index).unwrap_or_default(),
+        )
+    }
+
+    // `relative` is the path below the search path, using "/" between folders
+    pub fn matches(&self, relative: &str) -> bool {
+        self.include.iter().any(|x| pattern_matches(x, relative))
+            && !self.exclude.iter().any(|x| pattern_matches(x, relative))
+    }
+
+    fn excludes_folder(&self, relative: &str) -> bool {
+        self.exclude
+            .iter()
+            .filter(|x| x.ends_with('/'))
+            .any(|x| pattern_matches(x, &format!("{}/", relative)))
+    }
+}
+
+fn scan_dir(
+    root: &Path,
+    dir: &Path,
+    recursive: bool,
+    filter: &FileFilter,
+    out: &mut Vec<PathBuf>,
+) {
+    for file in match read_dir(dir) {
+        Ok(x) => x.collect(),
+        Err(_) => vec![],
+    } {
+        if let Ok(file) = file {
+            let path = file.path();
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            match file.file_type() {
+                Ok(x) if x.is_dir() => {
+                    if recursive && !filter.excludes_folder(&relative) {
+                        scan_dir(root, &path, recursive, filter, out);
+                    }
+                }
+                Ok(_) => {
+                    if filter.matches(&relative) {
+                        out.push(path);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+pub fn scan_search_path(root: &Path, recursive: bool, filter: &FileFilter) -> Vec<PathBuf> {
+    let mut out = vec![];
+    scan_dir(root, root, recursive, filter, &mut out);
+
+    out
+}
+
+// Every LIF file in the search paths, with the index of the search path it was found in
+pub fn scan_lif_files(settings: &SettingsData) -> Vec<(PathBuf, usize)> {
+    let mut out = vec![];
+    for (i, search_path) in settings.search_paths.iter().enumerate() {
+        let filter = FileFilter::from_settings(settings, i);
+        let recursive = settings.recursive_paths.row_data(i).unwrap_or(false);
+        for path in scan_search_path(Path::new(search_path.as_str()), recursive, &filter) {
+            out.push((path, i));
+        }
+    }
+
+    out
+}
+
+// The index of the search path a single file would be listed under, if any
+pub fn search_path_of(settings: &SettingsData, path: &Path) -> Option<usize> {
+    settings
+        .search_paths
+        .iter()
+        .enumerate()
+        .find(|(i, search_path)| {
+            let relative = match path.strip_prefix(search_path.as_str()) {
+                Ok(x) => x.to_string_lossy().replace('\\', "/"),
+                Err(_) => return false,
+            };
+            let recursive = settings.recursive_paths.row_data(*i).unwrap_or(false);
+
+            (recursive || !relative.contains('/'))
+                && FileFilter::from_settings(settings, *i).matches(&relative)
+        })
+        .map(|x| x.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        glob_match(
+            &pattern.chars().collect::<Vec<_>>(),
+            &text.chars().collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn star_stays_within_a_folder() {
+        assert!(glob("*.lif", "001-1-01.lif"));
+        assert!(glob("*", ""));
+        assert!(!glob("*.lif", "001-1-01.LIF"));
+        assert!(!glob("*.lif", "day1/001-1-01.lif"));
+        assert!(glob("day*/*.lif", "day1/001-1-01.lif"));
+    }
+
+    #[test]
+    fn double_star_crosses_folders() {
+        assert!(glob("**.lif", "day1/session2/001-1-01.lif"));
+        assert!(glob("day1/**/*.lif", "day1/session2/001-1-01.lif"));
+        assert!(glob("**/*.lif", "/001-1-01.lif"));
+        assert!(!glob("day1/**/*.lif", "day2/session2/001-1-01.lif"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob("00?-1-01.lif", "001-1-01.lif"));
+        assert!(!glob("00?-1-01.lif", "00-1-01.lif"));
+        assert!(!glob("00?-1-01.lif", "0012-1-01.lif"));
+        assert!(!glob("day?01.lif", "day/01.lif"));
+    }
+
+    #[test]
+    fn patterns_match_name_path_or_folder() {
+        assert!(pattern_matches("*.lif", "day1/001-1-01.lif"));
+        assert!(pattern_matches("day1/*.lif", "day1/001-1-01.lif"));
+        assert!(!pattern_matches("day1/*.lif", "day2/001-1-01.lif"));
+        assert!(pattern_matches("backup/", "day1/backup/001-1-01.lif"));
+        assert!(!pattern_matches("backup/", "backup.lif"));
+    }
+
+    #[test]
+    fn filter_includes_lif_files_without_include_patterns() {
+        let filter = FileFilter::new("", "");
+        assert!(filter.matches("001-1-01.lif"));
+        assert!(filter.matches("day1/001-1-01.LIF"));
+        assert!(!filter.matches("results.pdf"));
+        assert!(!filter.matches("lynx.evt.bak"));
+
+        let filter = FileFilter::new(" , \n", "test-*");
+        assert!(filter.matches("001-1-01.lif"));
+        assert!(!filter.matches("test-001.lif"));
+    }
+
+    #[test]
+    fn patterns_ignore_case() {
+        assert!(pattern_matches("*.lif", "001-1-01.LIF"));
+        assert!(pattern_matches("*.LIF", "001-1-01.lif"));
+        assert!(pattern_matches("Day1/*.lif", "day1/001-1-01.Lif"));
+        assert!(pattern_matches("backup/", "day1/Backup/001-1-01.lif"));
+    }
+
+    #[test]
+    fn filter_excludes_win_over_includes() {
+        let filter = FileFilter::new("*.lif", "backup/\ntest-*");
+        assert!(filter.matches("001-1-01.LIF"));
+        assert!(!filter.matches("notes.txt"));
+        assert!(!filter.matches("test-001.lif"));
+        assert!(!filter.matches("backup/001-1-01.lif"));
+        assert!(filter.excludes_folder("day1/backup"));
+        assert!(!filter.excludes_folder("day1"));
+    }
+}
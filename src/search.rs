@@ -0,0 +1,121 @@
+use crate::parse::RaceEvent;
+
+// Lowercases and strips accents so "Émilie" and "emilie" compare equal
+pub fn fold(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars().flat_map(|x| x.to_lowercase()) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => out.push('a'),
+            'æ' => out.push_str("ae"),
+            'ç' | 'ć' | 'č' => out.push('c'),
+            'ď' | 'đ' => out.push('d'),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => out.push('e'),
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => out.push('i'),
+            'ł' => out.push('l'),
+            'ñ' | 'ń' | 'ň' => out.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => out.push('o'),
+            'œ' => out.push_str("oe"),
+            'ř' => out.push('r'),
+            'ś' | 'š' | 'ș' | 'ş' => out.push('s'),
+            'ß' => out.push_str("ss"),
+            'ť' | 'ț' => out.push('t'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => out.push('u'),
+            'ý' | 'ÿ' => out.push('y'),
+            'ź' | 'ż' | 'ž' => out.push('z'),
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn edit_distance(first: &str, other: &str) -> usize {
+    let other = other.chars().collect::<Vec<_>>();
+    let mut previous = (0..=other.len()).collect::<Vec<_>>();
+    for (i, x) in first.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in other.iter().enumerate() {
+            let substitution = previous[j] + if x == *y { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[other.len()]
+}
+
+// Every word of the query has to appear in the text, allowing one typo in longer words
+pub fn fuzzy_match(text: &str, query: &str) -> bool {
+    let text = fold(text);
+    let words = text
+        .split(|x: char| !x.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+
+    fold(query).split_whitespace().all(|token| {
+        text.contains(token)
+            || (token.chars().count() >= 4
+                && words.iter().any(|word| edit_distance(word, token) <= 1))
+    })
+}
+
+// Everything in a race worth searching for, as one string
+pub fn race_search_text(file_name: &str, event: &RaceEvent) -> String {
+    let mut out = vec![
+        file_name.to_string(),
+        event.event.event_code.clone(),
+        event.event.event_name.clone(),
+        event.event.start_time.clone(),
+    ];
+    for competitor in &event.competitors {
+        out.push(competitor.first_name.clone());
+        out.push(competitor.last_name.clone());
+        out.push(competitor.club.clone());
+    }
+
+    out.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_ignores_case_and_accents() {
+        assert_eq!(fold("Émilie"), "emilie");
+        assert_eq!(fold("Ødegård"), "odegard");
+        assert_eq!(fold("STRAßE"), "strasse");
+    }
+
+    #[test]
+    fn edit_distance_counts_single_changes() {
+        assert_eq!(edit_distance("jansen", "jansen"), 0);
+        assert_eq!(edit_distance("jansen", "janssen"), 1);
+        assert_eq!(edit_distance("jansen", "jnsen"), 1);
+        assert_eq!(edit_distance("jansen", "hansen"), 1);
+        assert_eq!(edit_distance("jansen", "hanson"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn every_query_word_has_to_match() {
+        let text = "001-1-01.lif 500m Ladies Émilie Dubois Club Rapide";
+        assert!(fuzzy_match(text, ""));
+        assert!(fuzzy_match(text, "emilie"));
+        assert!(fuzzy_match(text, "DUBOIS 500m"));
+        assert!(fuzzy_match(text, "ladi"));
+        assert!(!fuzzy_match(text, "dubois 1000m"));
+    }
+
+    #[test]
+    fn longer_words_allow_one_typo() {
+        let text = "Ladies 500m Emilie Dubois";
+        assert!(fuzzy_match(text, "duboys"));
+        assert!(fuzzy_match(text, "emlie"));
+        assert!(fuzzy_match(text, "ladiess"));
+        assert!(!fuzzy_match(text, "dbios"));
+        // Short words have to match exactly
+        assert!(!fuzzy_match(text, "50m"));
+        assert!(!fuzzy_match("Kim Lee", "lea"));
+    }
+}
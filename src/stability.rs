@@ -0,0 +1,36 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Splits the paths into those ready to be parsed and those still being written. A file is ready
+// once its size and modified time hold still for `interval` and it can be opened, which fails on
+// Windows while FinishLynx still has it open for writing. Deleted files and folders are always
+// ready so they can be taken off the list.
+pub fn split_stable(paths: Vec<PathBuf>, interval: Duration) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let before = paths.iter().map(|x| file_stamp(x)).collect::<Vec<_>>();
+    if !interval.is_zero() && before.iter().any(|x| x.is_some()) {
+        std::thread::sleep(interval);
+    }
+
+    let mut stable = vec![];
+    let mut unstable = vec![];
+    for (path, before) in paths.into_iter().zip(before) {
+        if !path.is_file() {
+            stable.push(path);
+            continue;
+        }
+
+        if before.is_some() && file_stamp(&path) == before && File::open(&path).is_ok() {
+            stable.push(path);
+        } else {
+            unstable.push(path);
+        }
+    }
+
+    (stable, unstable)
+}
@@ -0,0 +1,327 @@
+use crate::parse::{RaceEvent, SkaterTime};
+use crate::team::{group_teams, is_team_event};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default)]
+pub struct ClubStanding {
+    pub rank: usize,
+    pub club: String,
+    pub points: u32,
+    pub races: u32,
+    pub wins: u32,
+}
+
+// The heats of the last round of each event, in heat order. Earlier rounds only decide who gets
+// there, so they don't score.
+fn final_rounds(events: &Vec<(PathBuf, RaceEvent)>) -> Vec<Vec<&RaceEvent>> {
+    let mut finals: Vec<Vec<&RaceEvent>> = vec![];
+    for (_, event) in events {
+        match finals
+            .iter_mut()
+            .find(|x| x[0].event.event_code == event.event.event_code)
+        {
+            Some(heats) => {
+                if event.event.round > heats[0].event.round {
+                    *heats = vec![event];
+                } else if event.event.round == heats[0].event.round {
+                    heats.push(event);
+                }
+            }
+            None => finals.push(vec![event]),
+        }
+    }
+    for heats in finals.iter_mut() {
+        heats.sort_by_key(|x| x.event.heat);
+    }
+
+    finals
+}
+
+// A final, B final and so on, as named in the event name
+fn is_final(event: &RaceEvent) -> bool {
+    event.event.event_name.to_lowercase().contains("final")
+}
+
+// Club or team, place in the heat and time of everyone who finished
+fn finishers(
+    event: &RaceEvent,
+    team_counting_skater: usize,
+) -> Vec<(String, u8, Option<SkaterTime>)> {
+    let placings = if is_team_event(event) {
+        group_teams(event, team_counting_skater)
+            .into_iter()
+            .map(|x| (x.team_name, x.place, x.time))
+            .collect::<Vec<_>>()
+    } else {
+        event
+            .competitors
+            .iter()
+            .map(|x| (x.club.clone(), x.place.unwrap_or(255), x.time))
+            .collect::<Vec<_>>()
+    };
+
+    placings
+        .into_iter()
+        .filter(|x| x.1 != 0 && x.1 != 255)
+        .collect()
+}
+
+// Final classification of an event from the heats of its last round. Heats named as finals place
+// one after the other, the B final behind the A final. Otherwise, as with long track pairs, the
+// round is one classification by time across every heat.
+fn classification(heats: &[&RaceEvent], team_counting_skater: usize) -> Vec<(String, usize)> {
+    if heats.len() == 1 || heats.iter().all(|x| is_final(x)) {
+        let mut out = vec![];
+        let mut placed_before = 0;
+        for event in heats {
+            let mut placed = 0;
+            for (club, place, _) in finishers(event, team_counting_skater) {
+                out.push((club, placed_before + place as usize));
+                placed = placed.max(place as usize);
+            }
+            placed_before += placed;
+        }
+        return out;
+    }
+
+    let timed = heats
+        .iter()
+        .flat_map(|x| finishers(x, team_counting_skater))
+        .filter_map(|(club, _, time)| {
+            time.filter(|x| !x.is_no_time())
+                .map(|x| (club, x.total_secs()))
+        })
+        .collect::<Vec<_>>();
+
+    // Equal times share a place
+    timed
+        .iter()
+        .map(|(club, time)| {
+            let faster = timed.iter().filter(|x| x.1 < *time).count();
+            (club.clone(), faster + 1)
+        })
+        .collect()
+}
+
+// Awards points by final classification in every event of the meet to the skater's club, or to
+// the team for team events, and ranks clubs by points, then wins, then name
+pub fn club_standings(
+    events: &Vec<(PathBuf, RaceEvent)>,
+    points_per_place: &Vec<u32>,
+    team_counting_skater: usize,
+) -> Vec<ClubStanding> {
+    let mut standings: Vec<ClubStanding> = vec![];
+    let mut award = |club: &str, place: usize| {
+        let club = club.trim();
+        if club.is_empty() {
+            return;
+        }
+
+        let standing = match standings.iter().position(|x| x.club == club) {
+            Some(i) => &mut standings[i],
+            None => {
+                standings.push(ClubStanding {
+                    club: club.to_string(),
+                    ..Default::default()
+                });
+                standings.last_mut().unwrap()
+            }
+        };
+
+        standing.points += points_per_place
+            .get(place - 1)
+            .copied()
+            .unwrap_or(0);
+        standing.races += 1;
+        if place == 1 {
+            standing.wins += 1;
+        }
+    };
+
+    for heats in final_rounds(events) {
+        for (club, place) in classification(&heats, team_counting_skater) {
+            award(&club, place);
+        }
+    }
+
+    standings.sort_by(|x, y| {
+        y.points
+            .cmp(&x.points)
+            .then(y.wins.cmp(&x.wins))
+            .then(x.club.cmp(&y.club))
+    });
+    for (i, standing) in standings.iter_mut().enumerate() {
+        standing.rank = i + 1;
+    }
+
+    standings
+}
+
+pub fn standings_csv(standings: &Vec<ClubStanding>) -> String {
+    let mut out = "Rank,Club,Points,Races,Wins\r\n".to_string();
+    for standing in standings {
+        out.push_str(&format!(
+            "{},\"{}\",{},{},{}\r\n",
+            standing.rank,
+            standing.club.replace("\"", ""),
+            standing.points,
+            standing.races,
+            standing.wins
+        ));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{CompetitorRow, EventRow, SkaterTime};
+
+    // A heat of `code` named `name`, placing each club in the order given with the time given
+    fn timed_race(
+        code: &str,
+        round: u8,
+        heat: u8,
+        name: &str,
+        finishers: &[(&str, f32)],
+    ) -> (PathBuf, RaceEvent) {
+        let event = RaceEvent {
+            event: EventRow {
+                event_code: code.to_string(),
+                round: Some(round),
+                heat: Some(heat),
+                event_name: name.to_string(),
+                ..Default::default()
+            },
+            competitors: finishers
+                .iter()
+                .enumerate()
+                .map(|(i, x)| CompetitorRow {
+                    place: Some(i as u8 + 1),
+                    lane: Some(i as u8 + 1),
+                    club: x.0.to_string(),
+                    time: Some(SkaterTime::from_secs(x.1)),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        (PathBuf::from(format!("{}-{}-{}.lif", code, round, heat)), event)
+    }
+
+    // A heat of `code`, placing each club in the order given a second apart
+    fn race(code: &str, round: u8, heat: u8, clubs: &[&str]) -> (PathBuf, RaceEvent) {
+        let finishers = clubs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (*x, 40.0 + i as f32))
+            .collect::<Vec<_>>();
+        timed_race(code, round, heat, "500m", &finishers)
+    }
+
+    fn points(standings: &[ClubStanding]) -> Vec<(&str, u32)> {
+        standings
+            .iter()
+            .map(|x| (x.club.as_str(), x.points))
+            .collect()
+    }
+
+    #[test]
+    fn only_the_final_round_scores() {
+        let events = vec![
+            race("1", 1, 1, &["A", "B"]),
+            race("1", 1, 2, &["C", "A"]),
+            race("1", 2, 1, &["B", "C"]),
+        ];
+        let standings = club_standings(&events, &vec![5, 3, 1], 3);
+        assert_eq!(points(&standings), vec![("B", 5), ("C", 3)]);
+        assert_eq!(standings[0].races, 1);
+        assert_eq!(standings[0].wins, 1);
+    }
+
+    #[test]
+    fn final_round_is_found_in_any_file_order() {
+        let events = vec![
+            race("1", 2, 1, &["B", "C"]),
+            race("1", 1, 1, &["A", "B"]),
+        ];
+        let standings = club_standings(&events, &vec![5, 3, 1], 3);
+        assert_eq!(points(&standings), vec![("B", 5), ("C", 3)]);
+    }
+
+    #[test]
+    fn b_final_places_after_a_final() {
+        // Listed out of order, the A final is still heat 1. The B final is faster, but places
+        // behind the A final all the same.
+        let events = vec![
+            timed_race("1", 2, 2, "500m Final B", &[("C", 41.0), ("D", 42.0)]),
+            timed_race("1", 2, 1, "500m Final A", &[("A", 43.0), ("B", 44.0)]),
+        ];
+        let standings = club_standings(&events, &vec![8, 6, 4, 2], 3);
+        assert_eq!(
+            points(&standings),
+            vec![("A", 8), ("B", 6), ("C", 4), ("D", 2)]
+        );
+        assert_eq!(standings[2].wins, 0);
+    }
+
+    #[test]
+    fn pairs_rank_by_time_across_the_round() {
+        let events = vec![
+            timed_race("1", 1, 1, "500m", &[("A", 40.5), ("B", 42.0)]),
+            timed_race("1", 1, 2, "500m", &[("C", 40.0), ("D", 41.0)]),
+            timed_race("1", 1, 3, "500m", &[("E", 42.0), ("F", 43.0)]),
+        ];
+        let standings = club_standings(&events, &vec![10, 8, 6, 4, 2, 1], 3);
+        // B and E tie on time and share fourth place
+        assert_eq!(
+            points(&standings),
+            vec![("C", 10), ("A", 8), ("D", 6), ("B", 4), ("E", 4), ("F", 1)]
+        );
+        assert_eq!(standings[0].wins, 1);
+        assert_eq!(standings[1].wins, 0);
+    }
+
+    #[test]
+    fn pairs_skip_skaters_without_a_time() {
+        let mut dnf = timed_race("1", 1, 2, "500m", &[("C", 39.0), ("D", 41.0)]);
+        dnf.1.competitors[0].place = Some(255);
+        let mut no_time = timed_race("1", 1, 1, "500m", &[("A", 40.0), ("B", 42.0)]);
+        no_time.1.competitors[1].time = Some(SkaterTime {
+            subsecond: -1.0,
+            ..Default::default()
+        });
+        let standings = club_standings(&vec![no_time, dnf], &vec![5, 3, 1], 3);
+        assert_eq!(points(&standings), vec![("A", 5), ("D", 3)]);
+    }
+
+    #[test]
+    fn events_score_separately() {
+        let events = vec![
+            race("1", 1, 1, &["A", "B"]),
+            race("2", 1, 1, &["B", "A"]),
+            race("3", 1, 1, &["B", ""]),
+        ];
+        let standings = club_standings(&events, &vec![5, 3], 3);
+        assert_eq!(points(&standings), vec![("B", 13), ("A", 8)]);
+        assert_eq!(standings[0].rank, 1);
+        assert_eq!(standings[0].races, 3);
+        assert_eq!(standings[1].wins, 1);
+    }
+
+    #[test]
+    fn ties_go_to_wins_then_name() {
+        let events = vec![
+            race("1", 1, 1, &["B", "C", "A"]),
+            race("2", 1, 1, &["C", "A", "B"]),
+        ];
+        let standings = club_standings(&events, &vec![2, 2, 0], 3);
+        assert_eq!(points(&standings), vec![("C", 4), ("B", 2), ("A", 2)]);
+
+        let events = vec![race("1", 1, 1, &["B", "A"]), race("2", 1, 1, &["A", "B"])];
+        let standings = club_standings(&events, &vec![1, 1], 3);
+        assert_eq!(points(&standings), vec![("A", 2), ("B", 2)]);
+    }
+}
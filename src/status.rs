@@ -0,0 +1,34 @@
+use crate::MainWindow;
+use native_dialog::MessageLevel;
+use slint::Weak;
+use std::sync::Mutex;
+
+// Where problems found off the UI thread are shown, there's no console on Windows to print them to
+static STATUS_WINDOW: Mutex<Option<Weak<MainWindow>>> = Mutex::new(None);
+
+pub fn set_status_window(main_window: &MainWindow) {
+    *STATUS_WINDOW.lock().unwrap() = Some(main_window.as_weak());
+}
+
+// Shows the message in the status line at the bottom of the window. Safe to call from any thread.
+pub fn report_error(message: impl Into<String>) {
+    let message = message.into();
+    eprintln!("{}", message);
+
+    let main_window_weak = STATUS_WINDOW.lock().unwrap().clone();
+    if let Some(main_window_weak) = main_window_weak {
+        let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+            main_window.set_status_message(message.into());
+        });
+    }
+}
+
+// For the result of something the user just did, which they should see before carrying on
+pub fn show_error(title: &str, message: impl Into<String>) {
+    let _ = native_dialog::DialogBuilder::message()
+        .set_level(MessageLevel::Error)
+        .set_title(title)
+        .set_text(message.into())
+        .alert()
+        .show();
+}
@@ -1,10 +1,23 @@
+use crate::auto_process::QueueItem;
+use crate::history::BestMark;
+use crate::lookup::SkaterAppearance;
+use crate::mass_start::MassStartScoring;
+use crate::metrics::{format_gap, format_lap_average, format_speed};
 use crate::parse::CompetitorRow;
+use crate::records::BrokenRecord;
+use crate::standings::ClubStanding;
+use crate::team::TeamRow;
 
 pub fn gen_table_row(competitor: CompetitorRow) -> Vec<String> {
     let mut out = vec![];
 
     out.push(match competitor.place.unwrap() {
         255 => "DNF".to_string(),
+        _ if competitor.qualification.is_qualified() => format!(
+            "{} {}",
+            competitor.place.unwrap(),
+            competitor.qualification.mark()
+        ),
         _ => competitor.place.unwrap().to_string(),
     });
 
@@ -28,8 +41,249 @@ pub fn gen_table_row(competitor: CompetitorRow) -> Vec<String> {
         && competitor.time.unwrap().subsecond == -1.0
     {
         time = "No Time".to_string();
+    } else {
+        if competitor.best_mark != BestMark::None {
+            time = format!("{} {}", time, competitor.best_mark.mark());
+        }
+        for record in &competitor.records_broken {
+            time = format!("{} {}", time, record);
+        }
     }
     out.push(time);
 
     out
 }
+
+// Optional derived columns, appended after the ones from gen_table_row
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricColumns {
+    pub gap_to_leader: bool,
+    pub gap_to_previous: bool,
+    pub speed: bool,
+    pub lap_average: bool,
+}
+
+impl MetricColumns {
+    pub fn all() -> Self {
+        Self {
+            gap_to_leader: true,
+            gap_to_previous: true,
+            speed: true,
+            lap_average: true,
+        }
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        let mut out = vec![];
+        if self.gap_to_leader {
+            out.push("Gap".to_string());
+        }
+        if self.gap_to_previous {
+            out.push("Gap Prev".to_string());
+        }
+        if self.speed {
+            out.push("km/h".to_string());
+        }
+        if self.lap_average {
+            out.push("Lap Avg".to_string());
+        }
+
+        out
+    }
+}
+
+pub fn gen_metrics_row(competitor: &CompetitorRow, columns: MetricColumns) -> Vec<String> {
+    let mut out = vec![];
+    if columns.gap_to_leader {
+        out.push(format_gap(competitor.metrics.gap_to_leader));
+    }
+    if columns.gap_to_previous {
+        out.push(format_gap(competitor.metrics.gap_to_previous));
+    }
+    if columns.speed {
+        out.push(format_speed(competitor.metrics.speed_kmh));
+    }
+    if columns.lap_average {
+        out.push(format_lap_average(competitor.metrics.lap_average));
+    }
+
+    out
+}
+
+pub fn gen_start_list_row(competitor: &CompetitorRow) -> Vec<String> {
+    vec![
+        competitor.lane.unwrap_or(0).to_string(),
+        match competitor.skater_id.unwrap_or(i32::MAX as u32) as i32 {
+            i32::MAX => "Missing".to_string(),
+            id => id.to_string(),
+        },
+        competitor.first_name.clone(),
+        competitor.last_name.clone(),
+        competitor.club.clone(),
+    ]
+}
+
+pub fn gen_points_header(scoring: &MassStartScoring) -> Vec<String> {
+    let mut out = vec![
+        "Rank".to_string(),
+        "ID".to_string(),
+        "First Name".to_string(),
+        "Last Name".to_string(),
+    ];
+    for lap in &scoring.sprint_laps {
+        out.push(format!("Lap {}", lap));
+    }
+    out.push("Finish".to_string());
+    out.push("Points".to_string());
+    out.push("Place".to_string());
+
+    out
+}
+
+pub fn gen_points_rows(scoring: &MassStartScoring) -> Vec<Vec<String>> {
+    let mut out = vec![];
+    for row in &scoring.rows {
+        let mut cur_row = vec![
+            row.rank.to_string(),
+            match row.skater_id as i32 {
+                i32::MAX => "Missing".to_string(),
+                _ => row.skater_id.to_string(),
+            },
+            row.first_name.clone(),
+            row.last_name.clone(),
+        ];
+        for points in &row.sprint_points {
+            cur_row.push(points.to_string());
+        }
+        cur_row.push(row.finish_points.to_string());
+        cur_row.push(row.total.to_string());
+        cur_row.push(match row.place {
+            255 => "DNF".to_string(),
+            _ => row.place.to_string(),
+        });
+        out.push(cur_row);
+    }
+
+    out
+}
+
+// Same as gen_points_rows, but with every sprint folded into one column for the preview table
+pub fn gen_points_preview_rows(scoring: &MassStartScoring) -> Vec<Vec<String>> {
+    let mut out = vec![];
+    for (row, full_row) in scoring.rows.iter().zip(gen_points_rows(scoring)) {
+        let sprints = scoring
+            .sprint_laps
+            .iter()
+            .zip(&row.sprint_points)
+            .filter(|x| *x.1 > 0)
+            .map(|x| format!("L{}: {}", x.0, x.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut cur_row = full_row[..4].to_vec();
+        cur_row.push(sprints);
+        cur_row.extend_from_slice(&full_row[full_row.len() - 3..]);
+        out.push(cur_row);
+    }
+
+    out
+}
+
+pub fn gen_team_row(team: &TeamRow) -> Vec<String> {
+    vec![
+        match team.place {
+            255 => "DNF".to_string(),
+            _ => team.place.to_string(),
+        },
+        match team.lane {
+            255 => "Missing".to_string(),
+            _ => team.lane.to_string(),
+        },
+        team.team_name.clone(),
+        team.members
+            .iter()
+            .map(|x| format!("{} {}", x.first_name, x.last_name))
+            .collect::<Vec<_>>()
+            .join(", "),
+        match team.time {
+            Some(time) => time.to_string(),
+            None => "No Time".to_string(),
+        },
+    ]
+}
+
+pub fn gen_broken_record_row(broken: &BrokenRecord) -> Vec<String> {
+    vec![
+        broken.record.kind_name(),
+        format!("{} m", broken.record.distance_m),
+        [broken.record.category.as_str(), broken.record.gender.as_str()]
+            .iter()
+            .filter(|x| !x.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" "),
+        broken.event_code.clone(),
+        format!("{} {}", broken.first_name, broken.last_name),
+        broken.club.clone(),
+        broken.time.to_string(),
+        match broken.record.holder.is_empty() {
+            true => broken.record.time.to_string(),
+            false => format!("{} ({})", broken.record.time, broken.record.holder),
+        },
+    ]
+}
+
+pub fn gen_category_cell(competitor: &CompetitorRow) -> String {
+    match competitor.category_place {
+        Some(place) => format!("{} ({})", competitor.category, place),
+        None => competitor.category.clone(),
+    }
+}
+
+pub fn gen_standing_row(standing: &ClubStanding) -> Vec<String> {
+    vec![
+        standing.rank.to_string(),
+        standing.club.clone(),
+        standing.points.to_string(),
+        standing.races.to_string(),
+        standing.wins.to_string(),
+    ]
+}
+
+pub fn gen_appearance_row(appearance: &SkaterAppearance) -> Vec<String> {
+    let row = gen_table_row(appearance.competitor.clone());
+    vec![
+        format!(
+            "{} {}",
+            appearance.event.event_code, appearance.event.event_name
+        ),
+        appearance.event.start_time.clone(),
+        row[1].clone(),
+        format!(
+            "{} {}",
+            appearance.competitor.first_name, appearance.competitor.last_name
+        ),
+        appearance.competitor.club.clone(),
+        row[0].clone(),
+        row[6].clone(),
+        appearance
+            .competitor
+            .splits
+            .iter()
+            .filter(|x| !x.is_no_time())
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+    ]
+}
+
+pub fn gen_queue_row(item: &QueueItem) -> Vec<String> {
+    vec![
+        item.path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        item.status.name().to_string(),
+        item.message.clone(),
+    ]
+}
@@ -0,0 +1,97 @@
+use crate::parse::{CompetitorRow, RaceEvent, SkaterTime};
+
+#[derive(Clone, Debug, Default)]
+pub struct TeamRow {
+    pub place: u8,
+    pub lane: u8,
+    pub team_name: String,
+    // Ordered by finish time, fastest first
+    pub members: Vec<CompetitorRow>,
+    pub time: Option<SkaterTime>,
+}
+
+fn has_time(competitor: &CompetitorRow) -> bool {
+    competitor.time.map(|t| !t.is_no_time()).unwrap_or(false)
+}
+
+// Team events say so in the name and list several skaters in the same lane. Either alone isn't
+// enough, mass starts put everyone in lane 0 and a "Team Cup" race can be individual.
+pub fn is_team_event(event: &RaceEvent) -> bool {
+    let name = event.event.event_name.to_lowercase();
+    if !(name.contains("team") || name.contains("relay") || name.contains("pursuit")) {
+        return false;
+    }
+
+    // Lane 0 and missing lanes mean the skater wasn't given one, not that they share it
+    let mut lanes = event
+        .competitors
+        .iter()
+        .filter_map(|x| x.lane)
+        .filter(|x| *x != 0 && *x != 255)
+        .collect::<Vec<_>>();
+    let count = lanes.len();
+    lanes.sort();
+    lanes.dedup();
+
+    lanes.len() < count
+}
+
+// Groups skaters into teams by lane. The team time is the time of the `counting_skater`th skater
+// across the line (the third for team pursuit), or the last member if the team is smaller than
+// that, as with relays that only list the team once.
+pub fn group_teams(event: &RaceEvent, counting_skater: usize) -> Vec<TeamRow> {
+    let mut teams: Vec<TeamRow> = vec![];
+    for competitor in &event.competitors {
+        let lane = competitor.lane.unwrap_or(255);
+        match teams.iter_mut().find(|x| x.lane == lane) {
+            Some(team) => team.members.push(competitor.clone()),
+            None => teams.push(TeamRow {
+                place: 255,
+                lane,
+                team_name: String::new(),
+                members: vec![competitor.clone()],
+                time: None,
+            }),
+        }
+    }
+
+    for team in teams.iter_mut() {
+        team.members.sort_by(|x, y| match (has_time(x), has_time(y)) {
+            (true, true) => x
+                .time
+                .unwrap()
+                .total_secs()
+                .total_cmp(&y.time.unwrap().total_secs()),
+            (a, b) => b.cmp(&a),
+        });
+
+        team.team_name = team
+            .members
+            .iter()
+            .map(|x| x.club.trim())
+            .find(|x| !x.is_empty())
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| format!("Lane {}", team.lane));
+
+        let counting = counting_skater.max(1).min(team.members.len());
+        team.time = team
+            .members
+            .get(counting - 1)
+            .filter(|x| has_time(x))
+            .and_then(|x| x.time);
+    }
+
+    teams.sort_by(|x, y| match (x.time, y.time) {
+        (Some(a), Some(b)) => a.total_secs().total_cmp(&b.total_secs()),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    let mut place = 1;
+    for team in teams.iter_mut() {
+        if team.time.is_some() {
+            team.place = place;
+            place += 1;
+        }
+    }
+
+    teams
+}